@@ -55,8 +55,12 @@ Type=simple
 User=$CURRENT_USER
 WorkingDirectory=$WORKING_DIR
 ExecStart=$BINARY_PATH
+ExecReload=/bin/kill -HUP \$MAINPID
 Restart=always
 RestartSec=10
+# Let LinkUp stop its ngrok children itself and send the "stopped" notifications
+KillMode=mixed
+TimeoutStopSec=30
 StandardOutput=journal
 StandardError=journal
 Environment="RUST_LOG=info"
@@ -0,0 +1,146 @@
+use anyhow::{Context, Result, anyhow};
+use clap::{Parser, Subcommand};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+use crate::control::InstanceStatus;
+use crate::ngrok::{NgrokManager, NgrokTunnel};
+
+#[derive(Parser)]
+#[command(name = "linkup", version, about = "LinkUp - Ngrok Manager")]
+pub struct Cli {
+    /// Path to config.toml
+    #[arg(short, long, global = true, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    /// Override `log_level` from [settings] (debug, info, warn, error)
+    #[arg(long, global = true, value_name = "LEVEL")]
+    pub log_level: Option<String>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Clone, Copy)]
+pub enum Command {
+    /// Start all ngrok instances and keep them running (default)
+    Run,
+    /// Load and check the configuration without starting ngrok
+    Validate,
+    /// Show the status of each instance
+    Status,
+    /// List the public URLs of each instance
+    #[command(alias = "list-tunnels")]
+    Tunnels,
+}
+
+pub fn validate(config_path: &Path, config: &Config) -> Result<()> {
+    let mut manager = NgrokManager::new();
+    let instances = crate::configure_instances(&mut manager, config)?;
+    let skipped = config.ngrok_instances.len() - instances.len();
+    if skipped > 0 {
+        return Err(anyhow!(
+            "Configuration {} is invalid: {skipped} instance(s) would be skipped",
+            config_path.display()
+        ));
+    }
+    println!(
+        "Configuration {} is valid: {} instance(s), {} webhook(s)",
+        config_path.display(),
+        instances.len(),
+        config.webhooks.len()
+    );
+    Ok(())
+}
+
+pub async fn status(config_path: &Path) -> Result<()> {
+    let instances = fetch_instances(config_path).await?;
+    println!("{:<20} {:<10} {:<8} REASON", "NAME", "STATUS", "TUNNELS");
+    for instance in instances {
+        println!(
+            "{:<20} {:<10} {:<8} {}",
+            instance.name,
+            instance.status,
+            instance.tunnels.len(),
+            instance.reason.unwrap_or_default()
+        );
+    }
+    Ok(())
+}
+
+pub async fn tunnels(config_path: &Path) -> Result<()> {
+    let instances = fetch_instances(config_path).await?;
+    println!(
+        "{:<20} {:<16} {:<45} ADDR",
+        "INSTANCE", "TUNNEL", "PUBLIC URL"
+    );
+    for instance in instances {
+        for tunnel in instance.tunnels {
+            println!(
+                "{:<20} {:<16} {:<45} {}",
+                instance.name, tunnel.name, tunnel.public_url, tunnel.config.addr
+            );
+        }
+    }
+    Ok(())
+}
+
+async fn fetch_instances(config_path: &Path) -> Result<Vec<InstanceStatus>> {
+    let settings = Config::load_settings(config_path).context("Failed to load configuration")?;
+    match settings.control_addr {
+        Some(addr) => fetch_from_daemon(addr, &settings.control_token).await,
+        None => {
+            let config = Config::load(config_path).context("Failed to load configuration")?;
+            fetch_from_ngrok(&config).await
+        }
+    }
+}
+
+async fn fetch_from_daemon(addr: SocketAddr, token: &str) -> Result<Vec<InstanceStatus>> {
+    let mut request = reqwest::Client::new().get(format!("http://{addr}/instances"));
+    if !token.is_empty() {
+        request = request.bearer_auth(token);
+    }
+    let response = request.send().await.with_context(|| {
+        format!("Failed to reach LinkUp control API at {addr}. Is LinkUp running?")
+    })?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        return Err(anyhow!("Control API returned {status}: {body}"));
+    }
+
+    response
+        .json()
+        .await
+        .context("Failed to parse control API response")
+}
+
+async fn fetch_from_ngrok(config: &Config) -> Result<Vec<InstanceStatus>> {
+    let mut manager = NgrokManager::new();
+    crate::configure_instances(&mut manager, config)?;
+
+    let mut instances = Vec::new();
+    for name in manager.instance_names() {
+        let web_addr = manager
+            .web_addr(&name)
+            .map(|addr| addr.to_string())
+            .unwrap_or_default();
+        let (status, reason, tunnels): (_, _, Vec<NgrokTunnel>) =
+            match manager.get_tunnels(&name).await {
+                Ok(tunnels) => ("running".to_string(), None, tunnels),
+                Err(e) => ("unreachable".to_string(), Some(e.to_string()), Vec::new()),
+            };
+        instances.push(InstanceStatus {
+            name,
+            status,
+            reason,
+            web_addr,
+            tunnels,
+        });
+    }
+
+    Ok(instances)
+}
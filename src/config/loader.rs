@@ -1,13 +1,50 @@
 use anyhow::{Context, Result};
+use serde::Deserialize;
 use std::fs;
 use std::path::Path;
 
-use super::models::Config;
+use super::models::{Config, Settings};
+use super::secrets;
+
+#[derive(Deserialize)]
+struct SettingsOnly {
+    settings: Settings,
+}
 
 impl Config {
     pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
         let content = fs::read_to_string(path).context("Failed to read config file")?;
-        let config: Self = toml::from_str(&content).context("Failed to parse config file")?;
+        let mut table: toml::Table =
+            toml::from_str(&content).context("Failed to parse config file")?;
+        secrets::interpolate_env(&mut table)?;
+
+        let mut config: Self = toml::Value::Table(table)
+            .try_into()
+            .context("Failed to parse config file")?;
+        let base_dir = path.parent().unwrap_or_else(|| Path::new("."));
+        config.resolve_secret_files(base_dir)?;
+        config.settings.state_dir = config.settings.state_dir.map(|dir| base_dir.join(dir));
+        config.validate()?;
         Ok(config)
     }
+
+    // Loads just `[settings]`, interpolating and resolving only its own values,
+    // for commands that talk to a running daemon.
+    pub fn load_settings<P: AsRef<Path>>(path: P) -> Result<Settings> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path).context("Failed to read config file")?;
+        let mut table: toml::Table =
+            toml::from_str(&content).context("Failed to parse config file")?;
+        table.retain(|key, _| key == "settings");
+        secrets::interpolate_env(&mut table)?;
+
+        let mut settings: Settings = toml::Value::Table(table)
+            .try_into::<SettingsOnly>()
+            .context("Failed to parse config file")?
+            .settings;
+        let base_dir = path.parent().unwrap_or_else(|| Path::new("."));
+        settings.resolve_secrets(base_dir)?;
+        Ok(settings)
+    }
 }
@@ -1,4 +1,9 @@
 pub mod loader;
 pub mod models;
+mod secrets;
+pub mod validator;
 
-pub use models::{Config, NgrokInstance, Settings, Webhook};
+pub use models::{
+    Config, ContentType, HttpMethod, NgrokInstance, Protocol, Settings, TunnelConfig, Webhook,
+    WebhookKind, WebhookTemplates,
+};
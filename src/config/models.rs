@@ -1,33 +1,365 @@
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::net::SocketAddr;
+use std::path::PathBuf;
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Deserialize, Serialize, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
 pub struct NgrokInstance {
     pub name: String,
+    #[serde(default)]
     pub authtoken: String,
-    pub port: u16,
-    pub protocol: String,
-    pub region: String,
+    #[serde(default)]
+    pub authtoken_file: Option<PathBuf>,
+    #[serde(default)]
+    pub port: Option<u16>,
+    #[serde(default)]
+    pub protocol: Option<Protocol>,
+    pub region: Region,
+    #[serde(default)]
+    pub web_port: Option<u16>,
+    #[serde(default)]
+    pub tunnels: Vec<TunnelConfig>,
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct TunnelConfig {
+    pub name: String,
+    #[serde(alias = "protocol")]
+    pub proto: Protocol,
+    #[serde(deserialize_with = "deserialize_addr")]
+    pub addr: String,
+    #[serde(default)]
+    pub hostname: Option<String>,
+    #[serde(default)]
+    pub domain: Option<String>,
+    #[serde(default)]
+    pub basic_auth: Vec<String>,
+    #[serde(default = "default_inspect")]
+    pub inspect: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Protocol {
+    Http,
+    Tcp,
+    Tls,
+    // Unknown values are reported by `Config::validate` together with the
+    // other problems instead of failing the whole parse.
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Region {
+    Us,
+    Eu,
+    Ap,
+    Au,
+    Sa,
+    Jp,
+    In,
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum WebhookKind {
+    Discord,
+    Slack,
+    Telegram,
+    Teams,
+    #[serde(rename = "google_chat")]
+    GoogleChat,
+    Generic,
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Deserialize, Serialize, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
 pub struct Webhook {
     pub name: String,
     #[serde(rename = "type")]
-    pub kind: String,
+    pub kind: WebhookKind,
+    #[serde(default)]
     pub url: String,
+    #[serde(default)]
+    pub url_file: Option<PathBuf>,
+    #[serde(default)]
+    pub bot_token: String,
+    #[serde(default)]
+    pub bot_token_file: Option<PathBuf>,
+    #[serde(default, deserialize_with = "deserialize_chat_id")]
+    pub chat_id: String,
+    #[serde(default)]
+    pub message_thread_id: Option<i64>,
+    #[serde(default)]
+    pub events: Vec<String>,
+    #[serde(default)]
+    pub instances: Vec<String>,
+    #[serde(default)]
+    pub templates: WebhookTemplates,
+    #[serde(default)]
+    pub method: Option<HttpMethod>,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    #[serde(default)]
+    pub content_type: Option<ContentType>,
+    #[serde(default)]
+    pub secret: String,
+    #[serde(default)]
+    pub secret_file: Option<PathBuf>,
     pub enabled: bool,
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum HttpMethod {
+    #[default]
+    #[serde(alias = "post")]
+    Post,
+    #[serde(alias = "put")]
+    Put,
+    #[serde(alias = "patch")]
+    Patch,
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ContentType {
+    #[default]
+    Json,
+    Form,
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct WebhookTemplates {
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub body: Option<String>,
+    #[serde(default)]
+    pub footer: Option<String>,
+    #[serde(default)]
+    pub payload: Option<serde_json::Value>,
+    // Telegram only: send the template text as MarkdownV2 instead of escaping it.
+    #[serde(default)]
+    pub raw: bool,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct Settings {
     pub check_interval_seconds: u64,
     pub auto_restart: bool,
     pub log_level: String,
+    #[serde(default = "default_shutdown_timeout")]
+    pub shutdown_timeout_seconds: u64,
+    #[serde(default = "default_startup_timeout")]
+    pub startup_timeout_seconds: u64,
+    #[serde(default)]
+    pub control_addr: Option<SocketAddr>,
+    #[serde(default)]
+    pub control_token: String,
+    #[serde(default)]
+    pub control_token_file: Option<PathBuf>,
+    #[serde(default = "default_restart_backoff_initial")]
+    pub restart_backoff_initial_seconds: u64,
+    #[serde(default = "default_restart_backoff_max")]
+    pub restart_backoff_max_seconds: u64,
+    #[serde(default = "default_max_restarts")]
+    pub max_restarts: usize,
+    #[serde(default = "default_restart_window")]
+    pub restart_window_seconds: u64,
+    #[serde(default = "default_healthy_reset")]
+    pub healthy_reset_seconds: u64,
+    #[serde(default)]
+    pub state_dir: Option<PathBuf>,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct Config {
     pub ngrok_instances: Vec<NgrokInstance>,
     pub webhooks: Vec<Webhook>,
     pub settings: Settings,
 }
+
+impl fmt::Debug for NgrokInstance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NgrokInstance")
+            .field("name", &self.name)
+            .field("authtoken", &"<redacted>")
+            .field("authtoken_file", &self.authtoken_file)
+            .field("port", &self.port)
+            .field("protocol", &self.protocol)
+            .field("region", &self.region)
+            .field("web_port", &self.web_port)
+            .field("tunnels", &self.tunnels)
+            .finish()
+    }
+}
+
+impl fmt::Debug for Webhook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Webhook")
+            .field("name", &self.name)
+            .field("kind", &self.kind)
+            .field("url", &self.url)
+            .field("url_file", &self.url_file)
+            .field("bot_token", &"<redacted>")
+            .field("bot_token_file", &self.bot_token_file)
+            .field("chat_id", &self.chat_id)
+            .field("message_thread_id", &self.message_thread_id)
+            .field("events", &self.events)
+            .field("instances", &self.instances)
+            .field("templates", &self.templates)
+            .field("method", &self.method)
+            .field("headers", &self.headers.keys().collect::<Vec<_>>())
+            .field("content_type", &self.content_type)
+            .field("secret", &"<redacted>")
+            .field("secret_file", &self.secret_file)
+            .field("enabled", &self.enabled)
+            .finish()
+    }
+}
+
+impl fmt::Debug for Settings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Settings")
+            .field("check_interval_seconds", &self.check_interval_seconds)
+            .field("auto_restart", &self.auto_restart)
+            .field("log_level", &self.log_level)
+            .field("shutdown_timeout_seconds", &self.shutdown_timeout_seconds)
+            .field("startup_timeout_seconds", &self.startup_timeout_seconds)
+            .field("control_addr", &self.control_addr)
+            .field("control_token", &"<redacted>")
+            .field("control_token_file", &self.control_token_file)
+            .field(
+                "restart_backoff_initial_seconds",
+                &self.restart_backoff_initial_seconds,
+            )
+            .field(
+                "restart_backoff_max_seconds",
+                &self.restart_backoff_max_seconds,
+            )
+            .field("max_restarts", &self.max_restarts)
+            .field("restart_window_seconds", &self.restart_window_seconds)
+            .field("healthy_reset_seconds", &self.healthy_reset_seconds)
+            .field("state_dir", &self.state_dir)
+            .finish()
+    }
+}
+
+impl NgrokInstance {
+    pub fn resolved_tunnels(&self) -> Vec<TunnelConfig> {
+        if !self.tunnels.is_empty() {
+            return self.tunnels.clone();
+        }
+
+        self.port
+            .map(|port| TunnelConfig {
+                name: self.name.clone(),
+                proto: self.protocol.unwrap_or(Protocol::Http),
+                addr: port.to_string(),
+                hostname: None,
+                domain: None,
+                basic_auth: Vec::new(),
+                inspect: default_inspect(),
+            })
+            .into_iter()
+            .collect()
+    }
+}
+
+impl Region {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Us => "us",
+            Self::Eu => "eu",
+            Self::Ap => "ap",
+            Self::Au => "au",
+            Self::Sa => "sa",
+            Self::Jp => "jp",
+            Self::In => "in",
+            Self::Unknown => "unknown",
+        }
+    }
+}
+
+fn default_inspect() -> bool {
+    true
+}
+
+fn default_shutdown_timeout() -> u64 {
+    10
+}
+
+fn default_startup_timeout() -> u64 {
+    30
+}
+
+fn default_restart_backoff_initial() -> u64 {
+    5
+}
+
+fn default_restart_backoff_max() -> u64 {
+    300
+}
+
+fn default_max_restarts() -> usize {
+    5
+}
+
+fn default_restart_window() -> u64 {
+    3600
+}
+
+fn default_healthy_reset() -> u64 {
+    600
+}
+
+fn deserialize_addr<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Addr {
+        Port(u16),
+        Address(String),
+    }
+
+    Ok(match Addr::deserialize(deserializer)? {
+        Addr::Port(port) => port.to_string(),
+        Addr::Address(address) => address,
+    })
+}
+
+fn deserialize_chat_id<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum ChatId {
+        Id(i64),
+        Username(String),
+    }
+
+    Ok(match ChatId::deserialize(deserializer)? {
+        ChatId::Id(id) => id.to_string(),
+        ChatId::Username(username) => username,
+    })
+}
@@ -0,0 +1,216 @@
+use anyhow::{Context, Result, anyhow};
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml::{Table, Value};
+
+use super::models::{Config, Settings};
+
+pub fn interpolate_env(table: &mut Table) -> Result<()> {
+    let mut errors = Vec::new();
+    for (key, value) in table.iter_mut() {
+        interpolate_value(key, value, &mut errors);
+    }
+
+    if errors.is_empty() {
+        return Ok(());
+    }
+    Err(anyhow!(
+        "Failed to interpolate environment variables:\n  - {}",
+        errors.join("\n  - ")
+    ))
+}
+
+fn interpolate_value(key: &str, value: &mut Value, errors: &mut Vec<String>) {
+    match value {
+        Value::String(text) => match interpolate_str(text) {
+            Ok(interpolated) => *text = interpolated,
+            Err(e) => errors.push(format!("{key}: {e}")),
+        },
+        Value::Array(items) => {
+            for (index, item) in items.iter_mut().enumerate() {
+                interpolate_value(&format!("{key}[{index}]"), item, errors);
+            }
+        }
+        Value::Table(table) => {
+            for (child, item) in table.iter_mut() {
+                interpolate_value(&format!("{key}.{child}"), item, errors);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn interpolate_str(text: &str) -> Result<String> {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("${") {
+        let after = &rest[start + 2..];
+        // `$${` is an escaped, literal `${`.
+        if let Some(prefix) = rest[..start].strip_suffix('$') {
+            result.push_str(prefix);
+            result.push_str("${");
+            rest = after;
+            continue;
+        }
+        result.push_str(&rest[..start]);
+        let end = after
+            .find('}')
+            .ok_or_else(|| anyhow!("unterminated `${{` in '{text}'"))?;
+        let name = &after[..end];
+        if name.is_empty() {
+            return Err(anyhow!("empty variable name in '{text}'"));
+        }
+        let value =
+            std::env::var(name).map_err(|_| anyhow!("environment variable {name} is not set"))?;
+        result.push_str(&value);
+        rest = &after[end + 1..];
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+fn read_secret(base_dir: &Path, path: &Path) -> Result<String> {
+    let path: PathBuf = if path.is_relative() {
+        base_dir.join(path)
+    } else {
+        path.to_path_buf()
+    };
+    let secret =
+        fs::read_to_string(&path).with_context(|| format!("failed to read {}", path.display()))?;
+    Ok(secret.trim_end_matches(['\r', '\n']).to_string())
+}
+
+impl Config {
+    pub(super) fn resolve_secret_files(&mut self, base_dir: &Path) -> Result<()> {
+        let mut errors = Vec::new();
+
+        for (index, instance) in self.ngrok_instances.iter_mut().enumerate() {
+            let Some(path) = &instance.authtoken_file else {
+                continue;
+            };
+            let key = format!("ngrok_instances[{index}]");
+            if !instance.authtoken.is_empty() {
+                errors.push(format!(
+                    "{key}: set either `authtoken` or `authtoken_file`, not both"
+                ));
+                continue;
+            }
+            match read_secret(base_dir, path) {
+                Ok(token) => instance.authtoken = token,
+                Err(e) => errors.push(format!("{key}.authtoken_file: {e:#}")),
+            }
+        }
+
+        for (index, webhook) in self.webhooks.iter_mut().enumerate() {
+            let key = format!("webhooks[{index}]");
+            if let Some(path) = &webhook.url_file {
+                if !webhook.url.is_empty() {
+                    errors.push(format!("{key}: set either `url` or `url_file`, not both"));
+                } else {
+                    match read_secret(base_dir, path) {
+                        Ok(url) => webhook.url = url,
+                        Err(e) => errors.push(format!("{key}.url_file: {e:#}")),
+                    }
+                }
+            }
+            if let Some(path) = &webhook.bot_token_file {
+                if !webhook.bot_token.is_empty() {
+                    errors.push(format!(
+                        "{key}: set either `bot_token` or `bot_token_file`, not both"
+                    ));
+                } else {
+                    match read_secret(base_dir, path) {
+                        Ok(token) => webhook.bot_token = token,
+                        Err(e) => errors.push(format!("{key}.bot_token_file: {e:#}")),
+                    }
+                }
+            }
+            if let Some(path) = &webhook.secret_file {
+                if !webhook.secret.is_empty() {
+                    errors.push(format!(
+                        "{key}: set either `secret` or `secret_file`, not both"
+                    ));
+                } else {
+                    match read_secret(base_dir, path) {
+                        Ok(secret) => webhook.secret = secret,
+                        Err(e) => errors.push(format!("{key}.secret_file: {e:#}")),
+                    }
+                }
+            }
+        }
+
+        self.settings.resolve_secret_files(base_dir, &mut errors);
+
+        if errors.is_empty() {
+            return Ok(());
+        }
+        Err(anyhow!(
+            "Failed to resolve secrets:\n  - {}",
+            errors.join("\n  - ")
+        ))
+    }
+}
+
+impl Settings {
+    pub(super) fn resolve_secrets(&mut self, base_dir: &Path) -> Result<()> {
+        let mut errors = Vec::new();
+        self.resolve_secret_files(base_dir, &mut errors);
+        if errors.is_empty() {
+            return Ok(());
+        }
+        Err(anyhow!(
+            "Failed to resolve secrets:\n  - {}",
+            errors.join("\n  - ")
+        ))
+    }
+
+    fn resolve_secret_files(&mut self, base_dir: &Path, errors: &mut Vec<String>) {
+        let Some(path) = &self.control_token_file else {
+            return;
+        };
+        if !self.control_token.is_empty() {
+            errors.push(
+                "settings: set either `control_token` or `control_token_file`, not both"
+                    .to_string(),
+            );
+            return;
+        }
+        match read_secret(base_dir, path) {
+            Ok(token) => self.control_token = token,
+            Err(e) => errors.push(format!("settings.control_token_file: {e:#}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_escaped_placeholders_literal() {
+        assert_eq!(
+            interpolate_str("pa$${ss}word $$ {x}").unwrap(),
+            "pa${ss}word $$ {x}"
+        );
+    }
+
+    #[test]
+    fn rejects_unterminated_and_empty_placeholders() {
+        let error = interpolate_str("token-${LINKUP_TOKEN").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "unterminated `${` in 'token-${LINKUP_TOKEN'"
+        );
+        let error = interpolate_str("${}").unwrap_err();
+        assert_eq!(error.to_string(), "empty variable name in '${}'");
+    }
+
+    #[test]
+    fn rejects_unset_variables() {
+        let error = interpolate_str("${LINKUP_TEST_UNSET_VARIABLE}").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "environment variable LINKUP_TEST_UNSET_VARIABLE is not set"
+        );
+    }
+}
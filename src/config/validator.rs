@@ -0,0 +1,309 @@
+use anyhow::{Result, anyhow};
+use reqwest::Url;
+use reqwest::header::{HeaderName, HeaderValue};
+use std::collections::HashSet;
+
+use super::models::{
+    Config, ContentType, HttpMethod, NgrokInstance, Protocol, Region, Webhook, WebhookKind,
+};
+use crate::webhook::event::EVENT_KINDS;
+use crate::webhook::filter::glob_match;
+use crate::webhook::template::MessageTemplates;
+
+impl Config {
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = Vec::new();
+
+        if self.ngrok_instances.is_empty() {
+            errors.push("ngrok_instances: at least one instance is required".to_string());
+        }
+
+        let mut instance_names = HashSet::new();
+        let mut web_ports = HashSet::new();
+        for (index, instance) in self.ngrok_instances.iter().enumerate() {
+            let key = format!("ngrok_instances[{index}]");
+            if !instance_names.insert(instance.name.as_str()) {
+                errors.push(format!(
+                    "{key}.name: duplicate instance name '{}'",
+                    instance.name
+                ));
+            }
+            if let Some(port) = instance.web_port
+                && !web_ports.insert(port)
+            {
+                errors.push(format!(
+                    "{key}.web_port: port {port} is already used by another instance"
+                ));
+            }
+            validate_instance(&key, instance, &mut errors);
+        }
+
+        let mut webhook_names = HashSet::new();
+        for (index, webhook) in self.webhooks.iter().enumerate() {
+            let key = format!("webhooks[{index}]");
+            if !webhook_names.insert(webhook.name.as_str()) {
+                errors.push(format!(
+                    "{key}.name: duplicate webhook name '{}'",
+                    webhook.name
+                ));
+            }
+            validate_webhook(&key, webhook, &mut errors);
+            validate_webhook_filters(&key, webhook, &instance_names, &mut errors);
+            if let Err(e) = MessageTemplates::from_config(&webhook.templates, false) {
+                errors.push(format!("{key}.templates.{e}"));
+            }
+        }
+
+        if self.settings.check_interval_seconds == 0 {
+            errors.push("settings.check_interval_seconds: must be greater than 0".to_string());
+        }
+
+        if self.settings.startup_timeout_seconds == 0 {
+            errors.push("settings.startup_timeout_seconds: must be greater than 0".to_string());
+        }
+
+        if let Some(addr) = self.settings.control_addr
+            && !addr.ip().is_loopback()
+        {
+            errors.push(format!(
+                "settings.control_addr: {addr} is not a loopback address; the control API is local only, use 127.0.0.1 or ::1"
+            ));
+        }
+
+        if self.settings.max_restarts == 0 {
+            errors.push("settings.max_restarts: must be greater than 0".to_string());
+        }
+        if self.settings.restart_backoff_max_seconds < self.settings.restart_backoff_initial_seconds
+        {
+            errors.push(
+                "settings.restart_backoff_max_seconds: must not be lower than restart_backoff_initial_seconds"
+                    .to_string(),
+            );
+        }
+
+        if errors.is_empty() {
+            return Ok(());
+        }
+
+        Err(anyhow!(
+            "Invalid configuration ({} problem(s)):\n  - {}",
+            errors.len(),
+            errors.join("\n  - ")
+        ))
+    }
+}
+
+fn validate_name(key: &str, name: &str, errors: &mut Vec<String>) {
+    if name.is_empty() {
+        errors.push(format!("{key}: must not be empty"));
+    } else if !name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        errors.push(format!(
+            "{key}: '{name}' may only contain letters, digits, '-' and '_'"
+        ));
+    }
+}
+
+fn validate_instance(key: &str, instance: &NgrokInstance, errors: &mut Vec<String>) {
+    validate_name(&format!("{key}.name"), &instance.name, errors);
+
+    if instance.authtoken.is_empty() || instance.authtoken.contains("your_") {
+        errors.push(format!(
+            "{key}.authtoken: a valid ngrok authtoken is required (set `authtoken` or `authtoken_file`)"
+        ));
+    }
+    if instance.protocol == Some(Protocol::Unknown) {
+        errors.push(format!("{key}.protocol: must be one of http, tcp, tls"));
+    }
+    if instance.region == Region::Unknown {
+        errors.push(format!(
+            "{key}.region: must be one of us, eu, ap, au, sa, jp, in"
+        ));
+    }
+    if instance.port == Some(0) {
+        errors.push(format!("{key}.port: must be between 1 and 65535"));
+    }
+    if instance.web_port == Some(0) {
+        errors.push(format!("{key}.web_port: must be between 1 and 65535"));
+    }
+    if instance.port.is_none() && instance.tunnels.is_empty() {
+        errors.push(format!(
+            "{key}: either `port` or at least one `tunnels` entry is required"
+        ));
+    }
+
+    let mut tunnel_names = HashSet::new();
+    for (index, tunnel) in instance.tunnels.iter().enumerate() {
+        let key = format!("{key}.tunnels[{index}]");
+        validate_name(&format!("{key}.name"), &tunnel.name, errors);
+        if !tunnel_names.insert(tunnel.name.as_str()) {
+            errors.push(format!(
+                "{key}.name: duplicate tunnel name '{}'",
+                tunnel.name
+            ));
+        }
+
+        let port = tunnel.addr.rsplit(':').next().unwrap_or_default();
+        if !matches!(port.parse::<u16>(), Ok(1..)) {
+            errors.push(format!(
+                "{key}.addr: '{}' must be a port or host:port with a port between 1 and 65535",
+                tunnel.addr
+            ));
+        }
+
+        if tunnel.proto == Protocol::Unknown {
+            errors.push(format!("{key}.proto: must be one of http, tcp, tls"));
+        }
+        if tunnel.proto == Protocol::Tcp {
+            if tunnel.hostname.is_some() {
+                errors.push(format!("{key}.hostname: not supported for tcp tunnels"));
+            }
+            if tunnel.domain.is_some() {
+                errors.push(format!("{key}.domain: not supported for tcp tunnels"));
+            }
+        }
+        if tunnel.proto != Protocol::Http && !tunnel.basic_auth.is_empty() {
+            errors.push(format!("{key}.basic_auth: only supported for http tunnels"));
+        }
+        for (auth_index, credentials) in tunnel.basic_auth.iter().enumerate() {
+            if !credentials.contains(':') {
+                errors.push(format!(
+                    "{key}.basic_auth[{auth_index}]: expected 'username:password'"
+                ));
+            }
+        }
+    }
+}
+
+fn validate_webhook(key: &str, webhook: &Webhook, errors: &mut Vec<String>) {
+    if webhook.name.is_empty() {
+        errors.push(format!("{key}.name: must not be empty"));
+    }
+
+    // The remaining checks depend on the webhook type.
+    if webhook.kind == WebhookKind::Unknown {
+        errors.push(format!(
+            "{key}.type: must be one of discord, slack, telegram, teams, google_chat, generic"
+        ));
+        return;
+    }
+
+    if webhook.kind == WebhookKind::Generic {
+        if webhook.method == Some(HttpMethod::Unknown) {
+            errors.push(format!("{key}.method: must be one of POST, PUT, PATCH"));
+        }
+        if webhook.content_type == Some(ContentType::Unknown) {
+            errors.push(format!("{key}.content_type: must be one of json, form"));
+        }
+        for (name, value) in &webhook.headers {
+            if HeaderName::from_bytes(name.as_bytes()).is_err() {
+                errors.push(format!(
+                    "{key}.headers: '{name}' is not a valid header name"
+                ));
+            } else if HeaderValue::from_str(value).is_err() {
+                errors.push(format!(
+                    "{key}.headers.{name}: value contains characters not allowed in a header"
+                ));
+            }
+        }
+    } else {
+        if webhook.method.is_some() {
+            errors.push(format!("{key}.method: only supported for generic webhooks"));
+        }
+        if !webhook.headers.is_empty() {
+            errors.push(format!(
+                "{key}.headers: only supported for generic webhooks"
+            ));
+        }
+        if webhook.content_type.is_some() {
+            errors.push(format!(
+                "{key}.content_type: only supported for generic webhooks"
+            ));
+        }
+        if webhook.templates.payload.is_some() {
+            errors.push(format!(
+                "{key}.templates.payload: only supported for generic webhooks"
+            ));
+        }
+        if !webhook.secret.is_empty() {
+            errors.push(format!("{key}.secret: only supported for generic webhooks"));
+        }
+    }
+
+    if webhook.kind == WebhookKind::Telegram {
+        if webhook.bot_token.is_empty() {
+            errors.push(format!(
+                "{key}.bot_token: required (set `bot_token` or `bot_token_file`)"
+            ));
+        }
+        if webhook.chat_id.is_empty() {
+            errors.push(format!("{key}.chat_id: required for telegram webhooks"));
+        }
+        // `url` optionally points at a self-hosted Bot API server.
+        if webhook.url.is_empty() {
+            return;
+        }
+    } else {
+        if !webhook.bot_token.is_empty() {
+            errors.push(format!(
+                "{key}.bot_token: only supported for telegram webhooks"
+            ));
+        }
+        if !webhook.chat_id.is_empty() {
+            errors.push(format!(
+                "{key}.chat_id: only supported for telegram webhooks"
+            ));
+        }
+        if webhook.templates.raw {
+            errors.push(format!(
+                "{key}.templates.raw: only supported for telegram webhooks"
+            ));
+        }
+        if webhook.message_thread_id.is_some() {
+            errors.push(format!(
+                "{key}.message_thread_id: only supported for telegram webhooks"
+            ));
+        }
+        if webhook.url.is_empty() {
+            errors.push(format!("{key}.url: required (set `url` or `url_file`)"));
+            return;
+        }
+    }
+
+    match Url::parse(&webhook.url) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") => {}
+        Ok(url) => errors.push(format!(
+            "{key}.url: unsupported scheme '{}', expected http or https",
+            url.scheme()
+        )),
+        Err(e) => errors.push(format!(
+            "{key}.url: '{}' is not a valid URL ({e})",
+            webhook.url
+        )),
+    }
+}
+
+fn validate_webhook_filters(
+    key: &str,
+    webhook: &Webhook,
+    instance_names: &HashSet<&str>,
+    errors: &mut Vec<String>,
+) {
+    for (index, pattern) in webhook.events.iter().enumerate() {
+        if !EVENT_KINDS.iter().any(|kind| glob_match(pattern, kind)) {
+            errors.push(format!(
+                "{key}.events[{index}]: '{pattern}' does not match any event (expected one of {})",
+                EVENT_KINDS.join(", ")
+            ));
+        }
+    }
+    for (index, pattern) in webhook.instances.iter().enumerate() {
+        if !instance_names.iter().any(|name| glob_match(pattern, name)) {
+            errors.push(format!(
+                "{key}.instances[{index}]: '{pattern}' does not match any configured instance"
+            ));
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use serde::{Deserialize, Serialize};
+use tokio::sync::oneshot;
+
+use crate::ngrok::{InstanceHealth, NgrokTunnel};
+
+#[derive(Debug, Clone, Copy)]
+pub enum InstanceAction {
+    Start,
+    Stop,
+    Restart,
+    Notify,
+}
+
+impl InstanceAction {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Start => "start",
+            Self::Stop => "stop",
+            Self::Restart => "restart",
+            Self::Notify => "notify",
+        }
+    }
+}
+
+pub enum CommandError {
+    NotFound(String),
+    Failed(anyhow::Error),
+}
+
+pub enum ControlCommand {
+    ListInstances {
+        reply: oneshot::Sender<Vec<InstanceStatus>>,
+    },
+    Instance {
+        name: String,
+        action: InstanceAction,
+        reply: oneshot::Sender<Result<(), CommandError>>,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InstanceStatus {
+    pub name: String,
+    pub status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    pub web_addr: String,
+    pub tunnels: Vec<NgrokTunnel>,
+}
+
+impl InstanceStatus {
+    pub fn new(
+        name: String,
+        health: InstanceHealth,
+        web_addr: String,
+        tunnels: Vec<NgrokTunnel>,
+    ) -> Self {
+        let (status, reason) = match health {
+            InstanceHealth::Healthy => ("healthy", None),
+            InstanceHealth::Degraded(reason) => ("degraded", Some(reason)),
+            InstanceHealth::Dead(reason) => ("dead", Some(reason)),
+        };
+
+        Self {
+            name,
+            status: status.to_string(),
+            reason,
+            web_addr,
+            tunnels,
+        }
+    }
+}
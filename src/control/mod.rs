@@ -0,0 +1,4 @@
+pub mod command;
+pub mod server;
+
+pub use command::{CommandError, ControlCommand, InstanceAction, InstanceStatus};
@@ -0,0 +1,237 @@
+use anyhow::{Context, Result};
+use axum::extract::{Path, Request, State};
+use axum::http::header::{AUTHORIZATION, HOST, ORIGIN};
+use axum::http::{HeaderMap, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use log::{error, info};
+use serde_json::{Value, json};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio::sync::{mpsc, oneshot, watch};
+use tokio::task::JoinHandle;
+
+use super::command::{CommandError, ControlCommand, InstanceAction, InstanceStatus};
+
+type Commands = mpsc::Sender<ControlCommand>;
+
+struct ApiError {
+    status: StatusCode,
+    message: String,
+}
+
+impl ApiError {
+    fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+
+    fn unavailable() -> Self {
+        Self::new(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "LinkUp is shutting down and cannot handle requests",
+        )
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.status, Json(json!({ "error": self.message }))).into_response()
+    }
+}
+
+// Loopback alone does not keep browsers out: any page can POST to 127.0.0.1,
+// and DNS rebinding lets it read the responses. Requests must name the
+// control address as their host, must not come from a web page, and must carry
+// the bearer token when one is configured.
+struct Guard {
+    hosts: Vec<String>,
+    token: String,
+}
+
+impl Guard {
+    fn new(addr: SocketAddr, token: String) -> Self {
+        Self {
+            hosts: vec![addr.to_string(), format!("localhost:{}", addr.port())],
+            token,
+        }
+    }
+
+    fn check(&self, headers: &HeaderMap) -> Result<(), ApiError> {
+        if headers.contains_key(ORIGIN) {
+            return Err(ApiError::new(
+                StatusCode::FORBIDDEN,
+                "Cross-origin requests are not allowed",
+            ));
+        }
+
+        let host = headers
+            .get(HOST)
+            .and_then(|host| host.to_str().ok())
+            .unwrap_or_default();
+        if !self
+            .hosts
+            .iter()
+            .any(|allowed| allowed.eq_ignore_ascii_case(host))
+        {
+            return Err(ApiError::new(
+                StatusCode::FORBIDDEN,
+                format!("Unexpected Host header '{host}'"),
+            ));
+        }
+
+        if self.token.is_empty() {
+            return Ok(());
+        }
+        let provided = headers
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .unwrap_or_default();
+        if provided.len() == self.token.len()
+            && openssl::memcmp::eq(provided.as_bytes(), self.token.as_bytes())
+        {
+            Ok(())
+        } else {
+            Err(ApiError::new(
+                StatusCode::UNAUTHORIZED,
+                "Missing or invalid bearer token",
+            ))
+        }
+    }
+}
+
+async fn guard(
+    State(guard): State<Arc<Guard>>,
+    request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    guard.check(request.headers())?;
+    Ok(next.run(request).await)
+}
+
+pub async fn spawn(
+    addr: SocketAddr,
+    token: String,
+    commands: Commands,
+    mut shutdown: watch::Receiver<bool>,
+) -> Result<JoinHandle<()>> {
+    let listener = TcpListener::bind(addr)
+        .await
+        .with_context(|| format!("Failed to bind control API to {addr}"))?;
+
+    let app = Router::new()
+        .route("/instances", get(list_instances))
+        .route("/instances/:name", get(get_instance))
+        .route("/instances/:name/start", post(start_instance))
+        .route("/instances/:name/stop", post(stop_instance))
+        .route("/instances/:name/restart", post(restart_instance))
+        .route("/instances/:name/notify", post(notify_instance))
+        .with_state(commands)
+        .layer(middleware::from_fn_with_state(
+            Arc::new(Guard::new(addr, token)),
+            guard,
+        ));
+
+    info!("Control API listening on http://{addr}");
+    Ok(tokio::spawn(async move {
+        let server = axum::serve(listener, app).with_graceful_shutdown(async move {
+            let _ = shutdown.changed().await;
+        });
+        if let Err(e) = server.await {
+            error!("Control API server failed: {e}");
+        }
+    }))
+}
+
+async fn fetch_instances(commands: &Commands) -> Result<Vec<InstanceStatus>, ApiError> {
+    let (reply, response) = oneshot::channel();
+    commands
+        .send(ControlCommand::ListInstances { reply })
+        .await
+        .map_err(|_| ApiError::unavailable())?;
+    response.await.map_err(|_| ApiError::unavailable())
+}
+
+async fn list_instances(
+    State(commands): State<Commands>,
+) -> Result<Json<Vec<InstanceStatus>>, ApiError> {
+    fetch_instances(&commands).await.map(Json)
+}
+
+async fn get_instance(
+    State(commands): State<Commands>,
+    Path(name): Path<String>,
+) -> Result<Json<InstanceStatus>, ApiError> {
+    fetch_instances(&commands)
+        .await?
+        .into_iter()
+        .find(|instance| instance.name == name)
+        .map(Json)
+        .ok_or_else(|| ApiError::new(StatusCode::NOT_FOUND, format!("Instance not found: {name}")))
+}
+
+async fn run_action(
+    commands: &Commands,
+    name: String,
+    action: InstanceAction,
+) -> Result<Json<Value>, ApiError> {
+    let (reply, response) = oneshot::channel();
+    commands
+        .send(ControlCommand::Instance {
+            name: name.clone(),
+            action,
+            reply,
+        })
+        .await
+        .map_err(|_| ApiError::unavailable())?;
+
+    match response.await.map_err(|_| ApiError::unavailable())? {
+        Ok(()) => Ok(Json(json!({
+            "instance": name,
+            "action": action.as_str(),
+            "ok": true,
+        }))),
+        Err(CommandError::NotFound(name)) => Err(ApiError::new(
+            StatusCode::NOT_FOUND,
+            format!("Instance not found: {name}"),
+        )),
+        Err(CommandError::Failed(e)) => Err(ApiError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            e.to_string(),
+        )),
+    }
+}
+
+async fn start_instance(
+    State(commands): State<Commands>,
+    Path(name): Path<String>,
+) -> Result<Json<Value>, ApiError> {
+    run_action(&commands, name, InstanceAction::Start).await
+}
+
+async fn stop_instance(
+    State(commands): State<Commands>,
+    Path(name): Path<String>,
+) -> Result<Json<Value>, ApiError> {
+    run_action(&commands, name, InstanceAction::Stop).await
+}
+
+async fn restart_instance(
+    State(commands): State<Commands>,
+    Path(name): Path<String>,
+) -> Result<Json<Value>, ApiError> {
+    run_action(&commands, name, InstanceAction::Restart).await
+}
+
+async fn notify_instance(
+    State(commands): State<Commands>,
+    Path(name): Path<String>,
+) -> Result<Json<Value>, ApiError> {
+    run_action(&commands, name, InstanceAction::Notify).await
+}
@@ -1,73 +1,154 @@
+mod cli;
 mod config;
+mod control;
+mod monitor;
 mod ngrok;
+mod signals;
+mod state;
 mod webhook;
 
 use anyhow::{Context, Result, anyhow};
-use config::{Config, NgrokInstance, Settings};
+use clap::Parser;
+use cli::{Cli, Command};
+use config::{Config, NgrokInstance};
 use log::{error, info, warn};
+use monitor::HealthMonitor;
 use ngrok::NgrokManager;
-use std::collections::HashSet;
+use signals::{ControlSignal, SignalListener};
+use state::StateStore;
 use std::path::PathBuf;
-use tokio::signal;
-use tokio::task::JoinHandle;
-use tokio::time::{Duration, sleep};
-use webhook::WebhookNotifier;
+use tokio::sync::{mpsc, watch};
+use tokio::time::Duration;
+use webhook::{NotificationEvent, WebhookNotifier};
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let config_path = get_config_path()?;
-    let config = Config::load(&config_path).context("Failed to load configuration")?;
-    let (check_interval, auto_restart_enabled) = init_logging(&config.settings);
+    let cli = Cli::parse();
+    let command = cli.command.unwrap_or(Command::Run);
+    let config_path = get_config_path(cli.config)?;
+    // Only `run` logs at the configured level; the other commands stay quiet.
+    let quiet_level = cli.log_level.clone().unwrap_or_else(|| "warn".to_string());
+
+    match command {
+        Command::Run => {
+            let mut config = Config::load(&config_path).context("Failed to load configuration")?;
+            if let Some(level) = &cli.log_level {
+                config.settings.log_level.clone_from(level);
+            }
+            init_logging(&config.settings.log_level);
+            run(config_path, config, cli.log_level).await
+        }
+        Command::Validate => {
+            let config = Config::load(&config_path).context("Failed to load configuration")?;
+            init_logging(&quiet_level);
+            cli::validate(&config_path, &config)
+        }
+        // These load the config themselves: with a control API they only need
+        // `[settings]`, so they don't trip over secrets only the service can read.
+        Command::Status => {
+            init_logging(&quiet_level);
+            cli::status(&config_path).await
+        }
+        Command::Tunnels => {
+            init_logging(&quiet_level);
+            cli::tunnels(&config_path).await
+        }
+    }
+}
 
+async fn run(config_path: PathBuf, mut config: Config, log_level: Option<String>) -> Result<()> {
     info!("Starting LinkUp - Ngrok Manager");
     info!("Loaded configuration from: {}", config_path.display());
     info!("Found {} ngrok instance(s)", config.ngrok_instances.len());
 
+    let mut signals = SignalListener::new()?;
+    let state = StateStore::load(config.settings.state_dir.as_deref())?;
     let notifier = WebhookNotifier::new(config.webhooks.clone());
     let mut manager = NgrokManager::new();
-    let valid_instances = configure_instances(&mut manager, &config)?;
-
-    info!("Starting all ngrok instances...");
-    manager.start_all().await?;
-    sleep(Duration::from_secs(5)).await;
-
-    let mut notified_instances = HashSet::new();
-    notify_initial_tunnels(
-        &manager,
-        &notifier,
-        &valid_instances,
-        &mut notified_instances,
-    )
-    .await;
-
-    let health_check_handle = spawn_health_monitor(
-        manager,
-        notifier,
-        notified_instances,
-        check_interval,
-        auto_restart_enabled,
+    configure_instances(&mut manager, &config)?;
+
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    let (reload_tx, reload_rx) = mpsc::channel(1);
+    let (command_tx, command_rx) = mpsc::channel(16);
+    let health_check_handle = HealthMonitor::new(manager, notifier, state).spawn(
+        &config.settings,
+        shutdown_rx.clone(),
+        reload_rx,
+        command_rx,
     );
 
-    info!("LinkUp is running. Press Ctrl+C to stop.");
-
-    match signal::ctrl_c().await {
-        Ok(()) => {
-            info!("Received shutdown signal, stopping...");
-            health_check_handle.abort();
-            info!("All instances stopped. Goodbye!");
-        }
-        Err(err) => {
-            error!("Unable to listen for shutdown signal: {err}");
+    let control_handle = match config.settings.control_addr {
+        Some(addr) => Some(
+            control::server::spawn(
+                addr,
+                config.settings.control_token.clone(),
+                command_tx,
+                shutdown_rx,
+            )
+            .await?,
+        ),
+        None => None,
+    };
+
+    info!("LinkUp is running. Press Ctrl+C to stop, send SIGHUP to reload the config.");
+
+    loop {
+        match signals.next().await {
+            Ok(ControlSignal::Reload) => match Config::load(&config_path) {
+                Ok(mut new_config) => {
+                    if let Some(level) = &log_level {
+                        new_config.settings.log_level.clone_from(level);
+                    }
+                    info!("Reloading configuration from: {}", config_path.display());
+                    if new_config.settings.log_level != config.settings.log_level {
+                        warn!("Changing log_level requires a restart of LinkUp");
+                    }
+                    if new_config.settings.state_dir != config.settings.state_dir {
+                        warn!("Changing state_dir requires a restart of LinkUp");
+                    }
+                    if new_config.settings.control_addr != config.settings.control_addr {
+                        warn!("Changing control_addr requires a restart of LinkUp");
+                    }
+                    config = new_config.clone();
+                    let _ = reload_tx.send(new_config).await;
+                }
+                Err(e) => error!("Failed to reload configuration, keeping the current one: {e:#}"),
+            },
+            Ok(ControlSignal::Shutdown(signal_name)) => {
+                info!("Received {signal_name}, stopping...");
+                break;
+            }
+            Err(err) => {
+                error!("Unable to listen for shutdown signal: {err}");
+                break;
+            }
         }
     }
 
+    let _ = shutdown_tx.send(true);
+    if let Some(handle) = control_handle {
+        let _ = handle.await;
+    }
+    let (mut manager, notifier, mut state) = health_check_handle
+        .await
+        .context("Health monitor task failed")?;
+
+    let shutdown_timeout = Duration::from_secs(config.settings.shutdown_timeout_seconds);
+    for instance in manager.shutdown(shutdown_timeout).await {
+        let event = NotificationEvent::Stopped { instance };
+        state.record(&event);
+        notifier.notify(event);
+    }
+    ngrok::remove_agent_config_dir();
+    notifier.flush(shutdown_timeout).await;
+    info!("All instances stopped. Goodbye!");
+
     Ok(())
 }
 
-fn get_config_path() -> Result<PathBuf> {
-    let args: Vec<String> = std::env::args().collect();
-    if args.len() > 1 {
-        return Ok(PathBuf::from(&args[1]));
+fn get_config_path(explicit: Option<PathBuf>) -> Result<PathBuf> {
+    if let Some(path) = explicit {
+        return Ok(path);
     }
 
     let current_dir_config = PathBuf::from("config.toml");
@@ -92,23 +173,20 @@ fn get_config_path() -> Result<PathBuf> {
          - Current directory\n\
          - ~/.config/linkup/config.toml\n\
          - /etc/linkup/config.toml\n\
-         Or specify the path as a command line argument."
+         Or specify the path with --config."
     ))
 }
 
-fn init_logging(settings: &Settings) -> (Duration, bool) {
+fn init_logging(log_level: &str) {
     unsafe {
-        std::env::set_var("RUST_LOG", &settings.log_level);
+        std::env::set_var("RUST_LOG", log_level);
     }
     env_logger::init();
-    (
-        Duration::from_secs(settings.check_interval_seconds),
-        settings.auto_restart,
-    )
 }
 
 fn configure_instances(manager: &mut NgrokManager, config: &Config) -> Result<Vec<NgrokInstance>> {
     let mut valid_instances = Vec::new();
+    manager.reserve_web_ports(&config.ngrok_instances);
     for instance in &config.ngrok_instances {
         match manager.add_instance(instance.clone()) {
             Ok(()) => {
@@ -129,90 +207,3 @@ fn configure_instances(manager: &mut NgrokManager, config: &Config) -> Result<Ve
         Ok(valid_instances)
     }
 }
-
-async fn notify_initial_tunnels(
-    manager: &NgrokManager,
-    notifier: &WebhookNotifier,
-    instances: &[NgrokInstance],
-    already_sent: &mut HashSet<String>,
-) {
-    for instance in instances {
-        match manager.get_tunnels(&instance.name).await {
-            Ok(tunnels) if tunnels.is_empty() => {
-                warn!("No tunnels found for instance '{}'", instance.name);
-            }
-            Ok(tunnels) => {
-                info!(
-                    "Tunnels for '{}': {} tunnel(s)",
-                    instance.name,
-                    tunnels.len()
-                );
-                let _ = notifier
-                    .notify_tunnel_created(&instance.name, &tunnels)
-                    .await;
-                already_sent.insert(instance.name.clone());
-            }
-            Err(e) => {
-                error!("Failed to get tunnels for '{}': {e}", instance.name);
-                let error_message = format!("Failed to start tunnels: {e}");
-                let _ = notifier.notify_error(&instance.name, &error_message).await;
-            }
-        }
-    }
-}
-
-fn spawn_health_monitor(
-    mut manager: NgrokManager,
-    notifier: WebhookNotifier,
-    mut already_sent: HashSet<String>,
-    check_interval: Duration,
-    auto_restart_enabled: bool,
-) -> JoinHandle<()> {
-    tokio::spawn(async move {
-        loop {
-            sleep(check_interval).await;
-
-            let health = manager.check_health();
-            for (name, is_healthy) in health {
-                if is_healthy {
-                    continue;
-                }
-
-                error!("Instance '{name}' is not healthy");
-
-                if !auto_restart_enabled {
-                    continue;
-                }
-
-                warn!("Auto-restarting instance '{name}'");
-                let _ = notifier.notify_restart(&name).await;
-
-                match manager.restart_instance(&name).await {
-                    Ok(()) => {
-                        info!("Successfully restarted instance '{name}'");
-                        sleep(Duration::from_secs(5)).await;
-                        if !already_sent.contains(&name) {
-                            match manager.get_tunnels(&name).await {
-                                Ok(tunnels) if tunnels.is_empty() => {}
-                                Ok(tunnels) => {
-                                    let _ = notifier.notify_tunnel_created(&name, &tunnels).await;
-                                    already_sent.insert(name.clone());
-                                }
-                                Err(e) => {
-                                    error!(
-                                        "Failed to fetch tunnels for '{name}' after restart: {e}"
-                                    );
-                                }
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        error!("Failed to restart instance '{name}': {e}");
-                        let error_message = format!("Failed to restart: {e}");
-                        let _ = notifier.notify_error(&name, &error_message).await;
-                    }
-                }
-            }
-        }
-    })
-}
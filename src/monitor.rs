@@ -0,0 +1,227 @@
+use anyhow::anyhow;
+use log::info;
+use tokio::sync::{mpsc, watch};
+use tokio::task::JoinHandle;
+use tokio::time::{Duration, timeout};
+
+use crate::config::{Config, Settings};
+use crate::control::{CommandError, ControlCommand, InstanceAction, InstanceStatus};
+use crate::ngrok::{
+    InstanceControl, InstanceEvent, InstanceEventKind, InstanceHealth, NgrokManager,
+};
+use crate::state::StateStore;
+use crate::webhook::{NotificationEvent, WebhookNotifier};
+
+// How long a status request waits for a supervisor that is busy starting or
+// restarting its instance.
+const HEALTH_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub struct HealthMonitor {
+    manager: NgrokManager,
+    notifier: WebhookNotifier,
+    state: StateStore,
+}
+
+impl HealthMonitor {
+    pub fn new(manager: NgrokManager, notifier: WebhookNotifier, state: StateStore) -> Self {
+        Self {
+            manager,
+            notifier,
+            state,
+        }
+    }
+
+    pub fn spawn(
+        mut self,
+        settings: &Settings,
+        mut shutdown: watch::Receiver<bool>,
+        mut reload: mpsc::Receiver<Config>,
+        mut commands: mpsc::Receiver<ControlCommand>,
+    ) -> JoinHandle<(NgrokManager, WebhookNotifier, StateStore)> {
+        let (events_tx, mut events) = mpsc::unbounded_channel();
+        // Notifications requested through the control API come back here so
+        // they always go to the current set of webhooks.
+        let (requested_tx, mut requested) = mpsc::unbounded_channel();
+        info!("Starting all ngrok instances...");
+        self.manager.start_all(settings, events_tx);
+
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = shutdown.changed() => break,
+                    Some(event) = events.recv() => self.notify(event),
+                    Some(event) = requested.recv() => self.notifier.notify(event),
+                    Some(config) = reload.recv() => self.apply_config(config).await,
+                    Some(command) = commands.recv() => self.handle_command(command, &requested_tx),
+                }
+            }
+
+            (self.manager, self.notifier, self.state)
+        })
+    }
+
+    fn notify(&mut self, event: InstanceEvent) {
+        let instance = event.instance;
+        let event = match event.kind {
+            InstanceEventKind::TunnelsReady(tunnels) => {
+                NotificationEvent::TunnelsReady { instance, tunnels }
+            }
+            InstanceEventKind::TunnelsChanged { previous, current } => {
+                NotificationEvent::TunnelUrlChanged {
+                    instance,
+                    previous,
+                    current,
+                }
+            }
+            InstanceEventKind::StartFailed(error) => NotificationEvent::InstanceError {
+                instance,
+                error: format!("Failed to start tunnels: {error}"),
+            },
+            InstanceEventKind::Degraded(reason) => NotificationEvent::InstanceError {
+                instance,
+                error: format!("Instance is degraded: {reason}"),
+            },
+            InstanceEventKind::Down(reason) => NotificationEvent::InstanceError {
+                instance,
+                error: format!("Instance is down: {reason}"),
+            },
+            InstanceEventKind::Restarting(reason) => {
+                NotificationEvent::Restarting { instance, reason }
+            }
+            InstanceEventKind::RestartFailed(error) => NotificationEvent::InstanceError {
+                instance,
+                error: format!("Failed to restart: {error}"),
+            },
+            InstanceEventKind::GaveUp { restarts, reason } => NotificationEvent::GaveUp {
+                instance,
+                restarts,
+                reason,
+            },
+            InstanceEventKind::Stopped => NotificationEvent::Stopped { instance },
+        };
+        self.send(event);
+    }
+
+    fn send(&mut self, event: NotificationEvent) {
+        if self.state.record(&event) {
+            self.notifier.notify(event);
+        } else {
+            info!(
+                "Tunnels for '{}' are unchanged since the last run, not announcing them again",
+                event.instance()
+            );
+        }
+    }
+
+    async fn apply_config(&mut self, config: Config) {
+        self.manager.update_settings(&config.settings);
+        let changes = self.manager.apply_instances(config.ngrok_instances).await;
+        for name in &changes.added {
+            info!("Added instance '{name}' from reloaded config");
+        }
+        for name in &changes.restarted {
+            info!("Restarted instance '{name}' with its new config");
+        }
+        for name in &changes.removed {
+            info!("Removed instance '{name}' from reloaded config");
+            self.send(NotificationEvent::Stopped {
+                instance: name.clone(),
+            });
+        }
+
+        self.notifier = WebhookNotifier::new(config.webhooks);
+        info!("Configuration reloaded");
+    }
+
+    // Control requests can take up to the startup timeout, so they run in their
+    // own tasks and never hold up notifications, reloads or shutdown.
+    fn handle_command(
+        &self,
+        command: ControlCommand,
+        requested: &mpsc::UnboundedSender<NotificationEvent>,
+    ) {
+        match command {
+            ControlCommand::ListInstances { reply } => {
+                let instances = self.manager.controls();
+                tokio::spawn(async move {
+                    let _ = reply.send(instance_statuses(instances).await);
+                });
+            }
+            ControlCommand::Instance {
+                name,
+                action,
+                reply,
+            } => {
+                let Ok(instance) = self.manager.control(&name) else {
+                    let _ = reply.send(Err(CommandError::NotFound(name)));
+                    return;
+                };
+                info!("Control API requested {} of '{name}'", action.as_str());
+                let requested = requested.clone();
+                tokio::spawn(async move {
+                    let result = run_action(&instance, action, &requested)
+                        .await
+                        .map_err(CommandError::Failed);
+                    let _ = reply.send(result);
+                });
+            }
+        }
+    }
+}
+
+async fn instance_statuses(instances: Vec<InstanceControl>) -> Vec<InstanceStatus> {
+    let tasks: Vec<_> = instances
+        .into_iter()
+        .map(|instance| tokio::spawn(instance_status(instance)))
+        .collect();
+    let mut statuses = Vec::new();
+    for task in tasks {
+        if let Ok(status) = task.await {
+            statuses.push(status);
+        }
+    }
+    statuses
+}
+
+async fn instance_status(instance: InstanceControl) -> InstanceStatus {
+    let health = timeout(HEALTH_TIMEOUT, instance.health())
+        .await
+        .unwrap_or_else(|_| {
+            InstanceHealth::Degraded("supervisor is busy starting or restarting".to_string())
+        });
+    let tunnels = instance.tunnels().await.unwrap_or_default();
+    InstanceStatus::new(
+        instance.name,
+        health,
+        instance.web_addr.to_string(),
+        tunnels,
+    )
+}
+
+async fn run_action(
+    instance: &InstanceControl,
+    action: InstanceAction,
+    requested: &mpsc::UnboundedSender<NotificationEvent>,
+) -> anyhow::Result<()> {
+    match action {
+        InstanceAction::Start => instance.start().await?,
+        InstanceAction::Stop => instance.stop().await?,
+        InstanceAction::Restart => instance.restart().await?,
+        InstanceAction::Notify => {
+            let tunnels = instance.tunnels().await?;
+            if tunnels.is_empty() {
+                return Err(anyhow!(
+                    "Instance '{}' has no tunnels online",
+                    instance.name
+                ));
+            }
+            requested
+                .send(NotificationEvent::TunnelsReady {
+                    instance: instance.name.clone(),
+                    tunnels,
+                })
+                .map_err(|_| anyhow!("LinkUp is shutting down"))?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,144 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs::{self, DirBuilder, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::net::SocketAddr;
+#[cfg(unix)]
+use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use crate::config::{NgrokInstance, Protocol, TunnelConfig};
+
+#[derive(Serialize)]
+struct AgentConfig<'a> {
+    version: &'static str,
+    authtoken: &'a str,
+    web_addr: String,
+    tunnels: BTreeMap<&'a str, AgentTunnel<'a>>,
+}
+
+#[derive(Serialize)]
+struct AgentTunnel<'a> {
+    proto: Protocol,
+    addr: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hostname: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    domain: Option<&'a str>,
+    #[serde(skip_serializing_if = "<[String]>::is_empty")]
+    basic_auth: &'a [String],
+    inspect: bool,
+}
+
+static CONFIG_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+// Agent configs hold the authtoken, so they live in a directory only this
+// process can enter, created fresh under `$XDG_RUNTIME_DIR` (or the temp
+// directory) so nobody else can pre-create or swap the files.
+fn config_dir() -> Result<&'static Path> {
+    if let Some(dir) = CONFIG_DIR.get() {
+        return Ok(dir);
+    }
+
+    let base = std::env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute() && dir.is_dir())
+        .unwrap_or_else(std::env::temp_dir);
+    let mut builder = DirBuilder::new();
+    #[cfg(unix)]
+    builder.mode(0o700);
+    loop {
+        let dir = base.join(format!(
+            "linkup-{}-{:016x}",
+            std::process::id(),
+            fastrand::u64(..)
+        ));
+        match builder.create(&dir) {
+            Ok(()) => {
+                let stored = CONFIG_DIR.get_or_init(|| dir.clone());
+                // Another instance got here first; use its directory instead.
+                if *stored != dir {
+                    let _ = fs::remove_dir(&dir);
+                }
+                return Ok(stored);
+            }
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+            Err(e) => {
+                return Err(e).with_context(|| {
+                    format!("Failed to create ngrok config directory {}", dir.display())
+                });
+            }
+        }
+    }
+}
+
+// Instance names are free-form, so anything that is not plainly safe in a file
+// name is hex-escaped.
+fn file_name(instance_name: &str) -> String {
+    let mut name = String::new();
+    for byte in instance_name.bytes() {
+        if byte.is_ascii_alphanumeric() || byte == b'-' {
+            name.push(char::from(byte));
+        } else {
+            let _ = write!(name, "_{byte:02x}");
+        }
+    }
+    name + ".yml"
+}
+
+pub fn write(instance: &NgrokInstance, web_addr: SocketAddr) -> Result<PathBuf> {
+    let tunnels: Vec<TunnelConfig> = instance.resolved_tunnels();
+    let config = AgentConfig {
+        version: "2",
+        authtoken: &instance.authtoken,
+        web_addr: web_addr.to_string(),
+        tunnels: tunnels
+            .iter()
+            .map(|tunnel| {
+                let agent_tunnel = AgentTunnel {
+                    proto: tunnel.proto,
+                    addr: &tunnel.addr,
+                    hostname: tunnel.hostname.as_deref(),
+                    domain: tunnel.domain.as_deref(),
+                    basic_auth: &tunnel.basic_auth,
+                    inspect: tunnel.inspect,
+                };
+                (tunnel.name.as_str(), agent_tunnel)
+            })
+            .collect(),
+    };
+
+    let content = serde_yaml::to_string(&config).context("Failed to render ngrok config")?;
+    let path = config_dir()?.join(file_name(&instance.name));
+    write_private(&path, &content)
+        .with_context(|| format!("Failed to write ngrok config {}", path.display()))?;
+    Ok(path)
+}
+
+pub fn remove(instance_name: &str) {
+    if let Some(dir) = CONFIG_DIR.get() {
+        let _ = fs::remove_file(dir.join(file_name(instance_name)));
+    }
+}
+
+pub fn remove_dir() {
+    if let Some(dir) = CONFIG_DIR.get() {
+        let _ = fs::remove_dir_all(dir);
+    }
+}
+
+fn write_private(path: &Path, content: &str) -> std::io::Result<()> {
+    // Left over from an earlier start of the same instance.
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != ErrorKind::NotFound => return Err(e),
+        _ => {}
+    }
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    options.mode(0o600).custom_flags(libc::O_NOFOLLOW);
+    options.open(path)?.write_all(content.as_bytes())
+}
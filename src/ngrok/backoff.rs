@@ -0,0 +1,108 @@
+use std::collections::VecDeque;
+use tokio::time::{Duration, Instant};
+
+use crate::config::Settings;
+
+#[derive(Clone)]
+pub struct RestartPolicy {
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    max_restarts: usize,
+    window: Duration,
+    healthy_reset: Duration,
+}
+
+impl RestartPolicy {
+    pub fn from_settings(settings: &Settings) -> Self {
+        Self {
+            initial_backoff: Duration::from_secs(settings.restart_backoff_initial_seconds),
+            max_backoff: Duration::from_secs(settings.restart_backoff_max_seconds),
+            max_restarts: settings.max_restarts,
+            window: Duration::from_secs(settings.restart_window_seconds),
+            healthy_reset: Duration::from_secs(settings.healthy_reset_seconds),
+        }
+    }
+
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    fn backoff(&self, attempts: u32) -> Duration {
+        let exponent = attempts.saturating_sub(1).min(16);
+        let base = self
+            .initial_backoff
+            .saturating_mul(1 << exponent)
+            .min(self.max_backoff);
+        // Equal jitter: wait at least half of the backoff, plus a random share of the rest.
+        let half = base / 2;
+        half + half.mul_f64(fastrand::f64())
+    }
+}
+
+pub enum RestartDecision {
+    Restart,
+    Wait(Duration),
+    GiveUp(usize),
+    GaveUp,
+}
+
+#[derive(Default)]
+pub struct RestartState {
+    attempts: u32,
+    restarts: VecDeque<Instant>,
+    next_attempt: Option<Instant>,
+    healthy_since: Option<Instant>,
+    gave_up: bool,
+}
+
+impl RestartState {
+    pub fn decide(&mut self, policy: &RestartPolicy, now: Instant) -> RestartDecision {
+        self.healthy_since = None;
+        if self.gave_up {
+            return RestartDecision::GaveUp;
+        }
+
+        while self
+            .restarts
+            .front()
+            .is_some_and(|restart| now.duration_since(*restart) > policy.window)
+        {
+            self.restarts.pop_front();
+        }
+
+        if self.restarts.len() >= policy.max_restarts {
+            self.gave_up = true;
+            return RestartDecision::GiveUp(self.restarts.len());
+        }
+
+        match self.next_attempt {
+            Some(next) if now < next => RestartDecision::Wait(next - now),
+            _ => RestartDecision::Restart,
+        }
+    }
+
+    pub fn record_restart(&mut self, policy: &RestartPolicy, now: Instant) {
+        self.attempts += 1;
+        self.restarts.push_back(now);
+        self.next_attempt = Some(now + policy.backoff(self.attempts));
+        self.healthy_since = None;
+    }
+
+    pub fn record_unhealthy(&mut self) {
+        self.healthy_since = None;
+    }
+
+    pub fn record_healthy(&mut self, policy: &RestartPolicy, now: Instant) -> bool {
+        if self.attempts == 0 && !self.gave_up {
+            return false;
+        }
+
+        let since = *self.healthy_since.get_or_insert(now);
+        if now.duration_since(since) < policy.healthy_reset {
+            return false;
+        }
+
+        *self = Self::default();
+        true
+    }
+}
@@ -0,0 +1,6 @@
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InstanceHealth {
+    Healthy,
+    Degraded(String),
+    Dead(String),
+}
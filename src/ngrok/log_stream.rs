@@ -0,0 +1,170 @@
+use log::{debug, error, info, warn};
+use serde_json::Value;
+use std::io::{BufRead, BufReader, Read};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NgrokEvent {
+    SessionEstablished,
+    TunnelStarted {
+        name: String,
+        url: String,
+        addr: String,
+    },
+    AuthFailed(String),
+    Reconnecting(String),
+    Error {
+        code: Option<String>,
+        message: String,
+    },
+}
+
+impl NgrokEvent {
+    pub fn parse(line: &str) -> Option<Self> {
+        let Ok(Value::Object(entry)) = serde_json::from_str::<Value>(line) else {
+            return Self::parse_plain(line);
+        };
+
+        let field = |key: &str| entry.get(key).and_then(Value::as_str).unwrap_or_default();
+        let lvl = field("lvl");
+        let msg = field("msg");
+        let err = match field("err") {
+            "<nil>" => "",
+            err => err,
+        };
+
+        if msg == "client session established" {
+            return Some(Self::SessionEstablished);
+        }
+        if msg == "started tunnel" {
+            return Some(Self::TunnelStarted {
+                name: field("name").to_string(),
+                url: field("url").to_string(),
+                addr: field("addr").to_string(),
+            });
+        }
+
+        let detail = if err.is_empty() { msg } else { err };
+        let code = error_code(detail);
+        if is_auth_failure(detail, code.as_deref()) {
+            return Some(Self::AuthFailed(detail.to_string()));
+        }
+        if msg.contains("reconnect") {
+            return Some(Self::Reconnecting(detail.to_string()));
+        }
+        if matches!(lvl, "eror" | "crit") || code.is_some() {
+            return Some(Self::Error {
+                code,
+                message: detail.to_string(),
+            });
+        }
+        None
+    }
+
+    fn parse_plain(line: &str) -> Option<Self> {
+        let line = line.trim();
+        let code = error_code(line);
+        if is_auth_failure(line, code.as_deref()) {
+            return Some(Self::AuthFailed(line.to_string()));
+        }
+        if code.is_some() || line.starts_with("ERROR") {
+            return Some(Self::Error {
+                code,
+                message: line.to_string(),
+            });
+        }
+        None
+    }
+
+    pub fn error_message(&self) -> Option<String> {
+        match self {
+            Self::AuthFailed(message) => Some(format!("authentication failed: {message}")),
+            Self::Error {
+                code: Some(code),
+                message,
+            } if !message.contains(code.as_str()) => Some(format!("{code}: {message}")),
+            Self::Error { message, .. } => Some(message.clone()),
+            _ => None,
+        }
+    }
+}
+
+fn error_code(text: &str) -> Option<String> {
+    let start = text.find("ERR_NGROK_")?;
+    let code: String = text[start..]
+        .chars()
+        .take_while(|c| c.is_ascii_alphanumeric() || *c == '_')
+        .collect();
+    Some(code)
+}
+
+fn is_auth_failure(text: &str, code: Option<&str>) -> bool {
+    matches!(
+        code,
+        Some("ERR_NGROK_105" | "ERR_NGROK_107" | "ERR_NGROK_4018")
+    ) || text.contains("authentication failed")
+}
+
+#[derive(Debug, Default)]
+pub struct LogState {
+    pub last_event: Option<NgrokEvent>,
+    pub last_error: Option<String>,
+}
+
+pub type SharedLogState = Arc<Mutex<LogState>>;
+
+pub fn spawn_reader<R>(instance_name: String, stream: R, state: SharedLogState)
+where
+    R: Read + Send + 'static,
+{
+    let thread_name = format!("ngrok-log-{instance_name}");
+    let spawned = thread::Builder::new().name(thread_name).spawn(move || {
+        for line in BufReader::new(stream).lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            handle_line(&instance_name, &line, &state);
+        }
+        debug!("ngrok log stream closed for instance: {instance_name}");
+    });
+
+    if let Err(e) = spawned {
+        error!("Failed to start ngrok log reader: {e}");
+    }
+}
+
+fn handle_line(instance_name: &str, line: &str, state: &SharedLogState) {
+    let Some(event) = NgrokEvent::parse(line) else {
+        debug!("[{instance_name}] ngrok: {line}");
+        return;
+    };
+
+    match &event {
+        NgrokEvent::SessionEstablished => {
+            info!("[{instance_name}] ngrok session established");
+        }
+        NgrokEvent::TunnelStarted { name, url, addr } => {
+            info!("[{instance_name}] ngrok tunnel '{name}' started: {url} -> {addr}");
+        }
+        NgrokEvent::Reconnecting(reason) => {
+            warn!("[{instance_name}] ngrok is reconnecting: {reason}");
+        }
+        NgrokEvent::AuthFailed(_) | NgrokEvent::Error { .. } => {
+            if let Some(message) = event.error_message() {
+                error!("[{instance_name}] ngrok: {message}");
+            }
+        }
+    }
+
+    let mut state = state.lock().unwrap_or_else(PoisonError::into_inner);
+    if event == NgrokEvent::SessionEstablished {
+        state.last_error = None;
+    } else if let Some(message) = event.error_message() {
+        state.last_error = Some(message);
+    }
+    state.last_event = Some(event);
+}
@@ -1,27 +1,86 @@
-use anyhow::{Context, Result, anyhow};
+use anyhow::{Result, anyhow};
 use log::{error, info};
-use serde::Deserialize;
-use std::collections::HashMap;
-use std::process::{Command, Stdio};
-use tokio::time::{Duration, sleep};
+use std::collections::{HashMap, HashSet};
+use std::net::{Ipv4Addr, SocketAddr};
+use tokio::sync::{mpsc, watch};
+use tokio::time::Duration;
 
+use super::health::InstanceHealth;
 use super::process::NgrokProcess;
-use super::tunnel::NgrokTunnel;
-use crate::config::NgrokInstance;
+use super::supervisor::{
+    InstanceEvent, Supervisor, SupervisorClient, SupervisorHandle, SupervisorSettings,
+};
+use super::tunnel::{self, NgrokTunnel};
+use crate::config::{NgrokInstance, Settings};
 
-#[derive(Deserialize)]
-struct TunnelsResponse {
-    tunnels: Vec<NgrokTunnel>,
+const DEFAULT_WEB_PORT: u16 = 4040;
+
+#[derive(Debug, Default)]
+pub struct InstanceChanges {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub restarted: Vec<String>,
+}
+
+struct ManagedInstance {
+    config: NgrokInstance,
+    web_addr: SocketAddr,
+    supervisor: Option<SupervisorHandle>,
+}
+
+struct Supervision {
+    settings: watch::Sender<SupervisorSettings>,
+    events: mpsc::UnboundedSender<InstanceEvent>,
+}
+
+// A snapshot of one instance that can be used from other tasks, so slow
+// actions never block the owner of the manager.
+#[derive(Clone)]
+pub struct InstanceControl {
+    pub name: String,
+    pub web_addr: SocketAddr,
+    supervisor: SupervisorClient,
+}
+
+impl InstanceControl {
+    pub async fn health(&self) -> InstanceHealth {
+        match self.supervisor.health().await {
+            Ok(receiver) => receiver.await.unwrap_or_else(|_| {
+                InstanceHealth::Dead("instance supervisor is not running".to_string())
+            }),
+            Err(e) => InstanceHealth::Dead(e.to_string()),
+        }
+    }
+
+    pub async fn tunnels(&self) -> Result<Vec<NgrokTunnel>> {
+        tunnel::fetch(self.web_addr).await
+    }
+
+    pub async fn start(&self) -> Result<()> {
+        self.supervisor.start().await
+    }
+
+    pub async fn stop(&self) -> Result<()> {
+        self.supervisor.stop().await
+    }
+
+    pub async fn restart(&self) -> Result<()> {
+        self.supervisor.restart().await
+    }
 }
 
 pub struct NgrokManager {
-    instances: HashMap<String, NgrokProcess>,
+    instances: HashMap<String, ManagedInstance>,
+    reserved_web_ports: HashSet<u16>,
+    supervision: Option<Supervision>,
 }
 
 impl NgrokManager {
     pub fn new() -> Self {
         Self {
             instances: HashMap::new(),
+            reserved_web_ports: HashSet::new(),
+            supervision: None,
         }
     }
 
@@ -34,129 +93,230 @@ impl NgrokManager {
             ));
         }
 
-        let process = NgrokProcess {
+        if config.resolved_tunnels().is_empty() {
+            return Err(anyhow!(
+                "Instance '{name}' has no tunnels. Set `port` or add `[[ngrok_instances.tunnels]]`."
+            ));
+        }
+
+        let web_port = match config.web_port {
+            Some(port) if self.web_port_in_use(port) => {
+                return Err(anyhow!(
+                    "Web port {port} for instance '{name}' is already used by another instance"
+                ));
+            }
+            Some(port) => port,
+            None => self.next_free_web_port()?,
+        };
+
+        let instance = ManagedInstance {
             config,
-            process: None,
+            web_addr: SocketAddr::from((Ipv4Addr::LOCALHOST, web_port)),
+            supervisor: None,
         };
 
-        self.instances.insert(name.clone(), process);
-        info!("Added ngrok instance: {name}");
+        self.instances.insert(name.clone(), instance);
+        info!("Added ngrok instance: {name} (web API on 127.0.0.1:{web_port})");
         Ok(())
     }
 
-    pub async fn start_all(&mut self) -> Result<()> {
-        let names: Vec<String> = self.instances.keys().cloned().collect();
-        for name in names {
-            match self.start_instance(&name).await {
-                Ok(()) => info!("Started ngrok instance: {name}"),
-                Err(e) => error!("Failed to start ngrok instance {name}: {e}"),
-            }
-        }
-        Ok(())
+    // Keeps the explicitly configured web ports free, so an instance without
+    // `web_port` never takes one that a later instance asks for.
+    pub fn reserve_web_ports(&mut self, instances: &[NgrokInstance]) {
+        self.reserved_web_ports = instances
+            .iter()
+            .filter_map(|instance| instance.web_port)
+            .collect();
     }
 
-    async fn start_instance_internal(&self, process: &mut NgrokProcess) -> Result<()> {
-        let child = Command::new("ngrok")
-            .arg(process.config.protocol.as_str())
-            .arg(process.config.port.to_string())
-            .arg("--authtoken")
-            .arg(&process.config.authtoken)
-            .arg("--region")
-            .arg(&process.config.region)
-            .arg("--log")
-            .arg("stdout")
-            .arg("--log-format")
-            .arg("json")
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()
-            .context("Failed to start ngrok process")?;
+    fn web_port_in_use(&self, port: u16) -> bool {
+        self.instances
+            .values()
+            .any(|instance| instance.web_addr.port() == port)
+    }
 
-        process.process = Some(child);
+    fn next_free_web_port(&self) -> Result<u16> {
+        (DEFAULT_WEB_PORT..=u16::MAX)
+            .find(|port| !self.reserved_web_ports.contains(port) && !self.web_port_in_use(*port))
+            .ok_or_else(|| anyhow!("No free port left for the ngrok web API"))
+    }
 
-        sleep(Duration::from_secs(3)).await;
+    pub fn start_all(&mut self, settings: &Settings, events: mpsc::UnboundedSender<InstanceEvent>) {
+        let (settings, _) = watch::channel(SupervisorSettings::from_settings(settings));
+        self.supervision = Some(Supervision { settings, events });
 
-        Ok(())
+        for name in self.instance_names() {
+            self.supervise(&name);
+        }
     }
 
-    pub async fn start_instance(&mut self, name: &str) -> Result<()> {
-        let config = self
-            .instances
-            .get(name)
-            .ok_or_else(|| anyhow!("Instance not found: {name}"))?
-            .config
-            .clone();
-
-        let mut process = NgrokProcess {
-            config,
-            process: None,
+    fn supervise(&mut self, name: &str) {
+        let (Some(supervision), Some(instance)) =
+            (self.supervision.as_ref(), self.instances.get_mut(name))
+        else {
+            return;
         };
+        if instance.supervisor.is_some() {
+            return;
+        }
 
-        self.start_instance_internal(&mut process).await?;
+        let process = NgrokProcess::new(instance.config.clone(), instance.web_addr);
+        instance.supervisor = Some(Supervisor::spawn(
+            process,
+            supervision.settings.subscribe(),
+            supervision.events.clone(),
+        ));
+    }
 
-        if let Some(entry) = self.instances.get_mut(name) {
-            entry.process = process.process;
+    pub fn update_settings(&self, settings: &Settings) {
+        if let Some(supervision) = &self.supervision {
+            supervision
+                .settings
+                .send_replace(SupervisorSettings::from_settings(settings));
         }
-
-        info!("Started ngrok instance: {name}");
-        Ok(())
     }
 
-    pub fn stop_instance(&mut self, name: &str) -> Result<()> {
-        let process = self
+    pub fn control(&self, name: &str) -> Result<InstanceControl> {
+        let instance = self
             .instances
-            .get_mut(name)
+            .get(name)
             .ok_or_else(|| anyhow!("Instance not found: {name}"))?;
-
-        process.kill()?;
-        info!("Stopped ngrok instance: {name}");
-        Ok(())
+        let supervisor = instance
+            .supervisor
+            .as_ref()
+            .ok_or_else(|| anyhow!("Instance '{name}' is not supervised"))?;
+        Ok(InstanceControl {
+            name: name.to_string(),
+            web_addr: instance.web_addr,
+            supervisor: supervisor.client(),
+        })
     }
 
-    pub async fn get_tunnels(&self, _name: &str) -> Result<Vec<NgrokTunnel>> {
-        let client = reqwest::Client::new();
-        let response = client
-            .get("http://127.0.0.1:4040/api/tunnels")
-            .send()
-            .await
-            .context("Failed to query ngrok API")?;
+    pub fn controls(&self) -> Vec<InstanceControl> {
+        self.instance_names()
+            .iter()
+            .filter_map(|name| self.control(name).ok())
+            .collect()
+    }
 
-        let status: TunnelsResponse = response
-            .json()
-            .await
-            .context("Failed to parse ngrok API response")?;
+    pub fn instance_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.instances.keys().cloned().collect();
+        names.sort();
+        names
+    }
 
-        Ok(status.tunnels)
+    pub fn web_addr(&self, name: &str) -> Option<SocketAddr> {
+        self.instances.get(name).map(|instance| instance.web_addr)
     }
 
-    pub async fn restart_instance(&mut self, name: &str) -> Result<()> {
-        info!("Restarting ngrok instance: {name}");
-        self.stop_instance(name)?;
-        sleep(Duration::from_secs(2)).await;
-        self.start_instance(name).await?;
+    pub async fn remove_instance(&mut self, name: &str) -> Result<()> {
+        let instance = self
+            .instances
+            .remove(name)
+            .ok_or_else(|| anyhow!("Instance not found: {name}"))?;
+        if let Some(supervisor) = instance.supervisor {
+            supervisor.remove().await;
+        }
+        info!("Removed ngrok instance: {name}");
         Ok(())
     }
 
-    pub fn check_health(&self) -> HashMap<String, bool> {
-        self.instances
+    pub async fn apply_instances(&mut self, instances: Vec<NgrokInstance>) -> InstanceChanges {
+        let mut changes = InstanceChanges::default();
+        self.reserve_web_ports(&instances);
+
+        let removed: Vec<String> = self
+            .instances
+            .keys()
+            .filter(|name| !instances.iter().any(|instance| &instance.name == *name))
+            .cloned()
+            .collect();
+        for name in removed {
+            match self.remove_instance(&name).await {
+                Ok(()) => changes.removed.push(name),
+                Err(e) => error!("Failed to remove ngrok instance {name}: {e}"),
+            }
+        }
+
+        // Instances holding an automatically assigned port that is now
+        // configured explicitly elsewhere move to a free one.
+        let displaced: Vec<String> = self
+            .instances
             .iter()
-            .map(|(name, process)| {
-                let is_running = process
-                    .process
-                    .as_ref()
-                    .is_some_and(|child| child.id() != 0);
-                (name.clone(), is_running)
+            .filter(|(_, managed)| {
+                managed.config.web_port.is_none()
+                    && self.reserved_web_ports.contains(&managed.web_addr.port())
             })
-            .collect()
+            .map(|(name, _)| name.clone())
+            .collect();
+        for name in &displaced {
+            if let Err(e) = self.remove_instance(name).await {
+                error!("Failed to stop ngrok instance {name}: {e}");
+            }
+        }
+
+        for instance in instances {
+            let name = instance.name.clone();
+            let existed = match self.instances.get(&name) {
+                Some(managed) if managed.config == instance => continue,
+                Some(_) => {
+                    if let Err(e) = self.remove_instance(&name).await {
+                        error!("Failed to stop ngrok instance {name}: {e}");
+                        continue;
+                    }
+                    true
+                }
+                None => displaced.contains(&name),
+            };
+
+            if let Err(e) = self.add_instance(instance) {
+                error!("Skipping invalid instance '{name}': {e}");
+                if existed {
+                    changes.removed.push(name);
+                }
+                continue;
+            }
+
+            self.supervise(&name);
+
+            if existed {
+                changes.restarted.push(name);
+            } else {
+                changes.added.push(name);
+            }
+        }
+
+        changes
     }
-}
 
-impl Drop for NgrokManager {
-    fn drop(&mut self) {
-        for (name, process) in &mut self.instances {
-            if let Err(e) = process.kill() {
-                error!("Error cleaning up ngrok instance {name}: {e}");
+    pub async fn get_tunnels(&self, name: &str) -> Result<Vec<NgrokTunnel>> {
+        let web_addr = self
+            .web_addr(name)
+            .ok_or_else(|| anyhow!("Instance not found: {name}"))?;
+        tunnel::fetch(web_addr).await
+    }
+
+    pub async fn shutdown(&mut self, timeout: Duration) -> Vec<String> {
+        let mut tasks = Vec::new();
+        for name in self.instance_names() {
+            let Some(supervisor) = self
+                .instances
+                .get_mut(&name)
+                .and_then(|instance| instance.supervisor.take())
+            else {
+                continue;
+            };
+            tasks.push((name, supervisor.shutdown(timeout).await));
+        }
+
+        let mut stopped = Vec::new();
+        for (name, task) in tasks {
+            match task.await {
+                Ok(true) => stopped.push(name),
+                Ok(false) => {}
+                Err(e) => error!("Supervisor for ngrok instance {name} failed: {e}"),
             }
         }
+        stopped
     }
 }
@@ -1,6 +1,14 @@
+mod agent_config;
+mod backoff;
+pub mod health;
+pub mod log_stream;
 pub mod manager;
 pub mod process;
+pub mod supervisor;
 pub mod tunnel;
 
-pub use manager::NgrokManager;
+pub use agent_config::remove_dir as remove_agent_config_dir;
+pub use health::InstanceHealth;
+pub use manager::{InstanceControl, NgrokManager};
+pub use supervisor::{InstanceEvent, InstanceEventKind};
 pub use tunnel::NgrokTunnel;
@@ -1,20 +1,295 @@
-use anyhow::{Context, Result};
-use log::info;
-use std::process::Child;
+use anyhow::{Context, Result, anyhow};
+use log::{error, info, warn};
+use std::net::SocketAddr;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::PoisonError;
+use tokio::time::{Duration, Instant, sleep};
 
+use super::agent_config;
+use super::health::InstanceHealth;
+use super::log_stream::{self, NgrokEvent, SharedLogState};
+use super::tunnel::{self, NgrokTunnel};
 use crate::config::NgrokInstance;
 
+const READY_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
 pub struct NgrokProcess {
     pub config: NgrokInstance,
     pub process: Option<Child>,
+    pub web_addr: SocketAddr,
+    pub log_state: SharedLogState,
+}
+
+pub enum ProcessState {
+    NotStarted,
+    Running,
+    Exited(ExitStatus),
 }
 
 impl NgrokProcess {
+    pub fn new(config: NgrokInstance, web_addr: SocketAddr) -> Self {
+        Self {
+            config,
+            process: None,
+            web_addr,
+            log_state: SharedLogState::default(),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    pub async fn start(&mut self, timeout: Duration) -> Result<Vec<NgrokTunnel>> {
+        if !matches!(self.state()?, ProcessState::Running) {
+            self.spawn()?;
+        }
+        self.wait_until_ready(timeout).await
+    }
+
+    fn spawn(&mut self) -> Result<()> {
+        let config_path = agent_config::write(&self.config, self.web_addr)?;
+
+        let mut child = Command::new("ngrok")
+            .arg("start")
+            .arg("--all")
+            .arg("--region")
+            .arg(self.config.region.as_str())
+            .arg("--config")
+            .arg(&config_path)
+            .arg("--log")
+            .arg("stdout")
+            .arg("--log-format")
+            .arg("json")
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context("Failed to start ngrok process")?;
+
+        self.log_state = SharedLogState::default();
+        let name = &self.config.name;
+        if let Some(stdout) = child.stdout.take() {
+            log_stream::spawn_reader(name.clone(), stdout, self.log_state.clone());
+        }
+        if let Some(stderr) = child.stderr.take() {
+            log_stream::spawn_reader(name.clone(), stderr, self.log_state.clone());
+        }
+
+        self.process = Some(child);
+        Ok(())
+    }
+
+    async fn wait_until_ready(&mut self, timeout: Duration) -> Result<Vec<NgrokTunnel>> {
+        let expected = self.expected_tunnels();
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            if let ProcessState::Exited(status) = self.state()? {
+                return Err(match self.last_error() {
+                    Some(last_error) => {
+                        anyhow!("ngrok exited during startup ({status}): {last_error}")
+                    }
+                    None => anyhow!("ngrok exited during startup ({status})"),
+                });
+            }
+            if let Some(NgrokEvent::AuthFailed(message)) = self.last_event() {
+                return Err(anyhow!("ngrok authentication failed: {message}"));
+            }
+
+            let missing: Vec<&str> = match self.tunnels().await {
+                Ok(tunnels) => {
+                    let missing = missing_tunnels(&expected, &tunnels);
+                    if missing.is_empty() {
+                        return Ok(tunnels);
+                    }
+                    missing
+                }
+                Err(_) => expected.iter().map(String::as_str).collect(),
+            };
+
+            if Instant::now() >= deadline {
+                let mut message = format!(
+                    "tunnels not online after {}s: {}",
+                    timeout.as_secs(),
+                    missing.join(", ")
+                );
+                if let Some(last_error) = self.last_error() {
+                    message.push_str(&format!(" (last ngrok error: {last_error})"));
+                }
+                return Err(anyhow!(message));
+            }
+            sleep(READY_POLL_INTERVAL).await;
+        }
+    }
+
     pub fn kill(&mut self) -> Result<()> {
         if let Some(mut child) = self.process.take() {
             child.kill().context("Failed to kill ngrok process")?;
+            let _ = child.wait();
             info!("Killed ngrok process for instance: {}", self.config.name);
         }
+        agent_config::remove(&self.config.name);
         Ok(())
     }
+
+    // Asks ngrok to exit: SIGTERM on Unix, a plain kill elsewhere.
+    pub fn terminate(&mut self) -> Result<()> {
+        let Some(child) = self.process.as_mut() else {
+            return Ok(());
+        };
+
+        #[cfg(unix)]
+        {
+            let pid = libc::pid_t::try_from(child.id()).context("Invalid ngrok process id")?;
+            // SAFETY: `pid` belongs to a child we spawned and have not reaped yet.
+            if unsafe { libc::kill(pid, libc::SIGTERM) } != 0 {
+                return Err(std::io::Error::last_os_error())
+                    .context("Failed to send SIGTERM to ngrok process");
+            }
+        }
+        #[cfg(not(unix))]
+        child.kill().context("Failed to stop ngrok process")?;
+        Ok(())
+    }
+
+    pub async fn shutdown(&mut self, timeout: Duration) -> bool {
+        let name = self.config.name.clone();
+        if self.process.is_none() {
+            return false;
+        }
+
+        match self.terminate() {
+            Ok(()) => info!("Asked ngrok instance {name} to stop"),
+            Err(e) => error!("Failed to terminate ngrok instance {name}: {e}"),
+        }
+
+        let deadline = Instant::now() + timeout;
+        loop {
+            match self.state() {
+                Ok(ProcessState::Running) => {}
+                Ok(_) => {
+                    agent_config::remove(&name);
+                    info!("Stopped ngrok instance: {name}");
+                    return true;
+                }
+                Err(e) => error!("Failed to check ngrok instance {name}: {e}"),
+            }
+
+            if Instant::now() >= deadline {
+                break;
+            }
+            sleep(Duration::from_millis(100)).await;
+        }
+
+        warn!("Ngrok instance {name} did not exit within {timeout:?}, killing it");
+        match self.kill() {
+            Ok(()) => true,
+            Err(e) => {
+                error!("Failed to kill ngrok instance {name}: {e}");
+                false
+            }
+        }
+    }
+
+    pub fn state(&mut self) -> Result<ProcessState> {
+        let Some(child) = self.process.as_mut() else {
+            return Ok(ProcessState::NotStarted);
+        };
+
+        match child
+            .try_wait()
+            .context("Failed to query ngrok process status")?
+        {
+            Some(status) => {
+                self.process = None;
+                Ok(ProcessState::Exited(status))
+            }
+            None => Ok(ProcessState::Running),
+        }
+    }
+
+    fn expected_tunnels(&self) -> Vec<String> {
+        self.config
+            .resolved_tunnels()
+            .into_iter()
+            .map(|tunnel| tunnel.name)
+            .collect()
+    }
+
+    pub async fn tunnels(&self) -> Result<Vec<NgrokTunnel>> {
+        tunnel::fetch(self.web_addr).await
+    }
+
+    pub async fn check_health(&mut self) -> InstanceHealth {
+        let expected = self.expected_tunnels();
+        let last_event = self.last_event();
+
+        match self.state() {
+            Ok(ProcessState::Running) => {}
+            Ok(ProcessState::NotStarted) => {
+                return InstanceHealth::Dead("ngrok process is not running".to_string());
+            }
+            Ok(ProcessState::Exited(status)) => {
+                let reason = match self.last_error() {
+                    Some(last_error) => format!("ngrok process exited ({status}): {last_error}"),
+                    None => format!("ngrok process exited ({status})"),
+                };
+                return InstanceHealth::Dead(reason);
+            }
+            Err(e) => return InstanceHealth::Dead(e.to_string()),
+        }
+
+        match self.tunnels().await {
+            Ok(tunnels) if tunnels.is_empty() => match last_event {
+                Some(NgrokEvent::Reconnecting(reason)) => {
+                    InstanceHealth::Degraded(format!("ngrok is reconnecting: {reason}"))
+                }
+                _ => InstanceHealth::Degraded("no tunnels are online".to_string()),
+            },
+            Ok(tunnels) => {
+                let missing = missing_tunnels(&expected, &tunnels);
+                if missing.is_empty() {
+                    InstanceHealth::Healthy
+                } else {
+                    InstanceHealth::Degraded(format!("tunnels not online: {}", missing.join(", ")))
+                }
+            }
+            Err(e) => InstanceHealth::Degraded(format!("local API unreachable: {e}")),
+        }
+    }
+
+    pub fn last_event(&self) -> Option<NgrokEvent> {
+        let state = self
+            .log_state
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        state.last_event.clone()
+    }
+
+    pub fn last_error(&self) -> Option<String> {
+        let state = self
+            .log_state
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        state.last_error.clone()
+    }
+}
+
+fn missing_tunnels<'a>(expected: &'a [String], tunnels: &[NgrokTunnel]) -> Vec<&'a str> {
+    expected
+        .iter()
+        .filter(|name| !tunnels.iter().any(|t| &t.name == *name))
+        .map(String::as_str)
+        .collect()
+}
+
+impl Drop for NgrokProcess {
+    fn drop(&mut self) {
+        if self.process.is_none() {
+            return;
+        }
+        if let Err(e) = self.kill() {
+            error!("Error cleaning up ngrok instance {}: {e}", self.config.name);
+        }
+    }
 }
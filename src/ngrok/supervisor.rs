@@ -0,0 +1,444 @@
+use anyhow::{Result, anyhow};
+use log::{error, info, warn};
+use std::mem::discriminant;
+use tokio::sync::{mpsc, oneshot, watch};
+use tokio::task::JoinHandle;
+use tokio::time::{Duration, Instant, sleep_until};
+
+use super::backoff::{RestartDecision, RestartPolicy, RestartState};
+use super::health::InstanceHealth;
+use super::process::NgrokProcess;
+use super::tunnel::NgrokTunnel;
+use crate::config::Settings;
+
+#[derive(Clone)]
+pub struct SupervisorSettings {
+    check_interval: Duration,
+    startup_timeout: Duration,
+    auto_restart: bool,
+    restart_policy: RestartPolicy,
+}
+
+impl SupervisorSettings {
+    pub fn from_settings(settings: &Settings) -> Self {
+        Self {
+            check_interval: Duration::from_secs(settings.check_interval_seconds),
+            startup_timeout: Duration::from_secs(settings.startup_timeout_seconds),
+            auto_restart: settings.auto_restart,
+            restart_policy: RestartPolicy::from_settings(settings),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct InstanceEvent {
+    pub instance: String,
+    pub kind: InstanceEventKind,
+}
+
+#[derive(Debug)]
+pub enum InstanceEventKind {
+    TunnelsReady(Vec<NgrokTunnel>),
+    TunnelsChanged {
+        previous: Vec<NgrokTunnel>,
+        current: Vec<NgrokTunnel>,
+    },
+    StartFailed(String),
+    Degraded(String),
+    Down(String),
+    Restarting(String),
+    RestartFailed(String),
+    GaveUp {
+        restarts: usize,
+        reason: String,
+    },
+    Stopped,
+}
+
+enum SupervisorCommand {
+    Health {
+        reply: oneshot::Sender<InstanceHealth>,
+    },
+    Start {
+        reply: oneshot::Sender<Result<()>>,
+    },
+    Stop {
+        reply: oneshot::Sender<Result<()>>,
+    },
+    Restart {
+        reply: oneshot::Sender<Result<()>>,
+    },
+    Shutdown {
+        timeout: Duration,
+    },
+}
+
+pub struct SupervisorHandle {
+    client: SupervisorClient,
+    task: JoinHandle<bool>,
+}
+
+impl SupervisorHandle {
+    pub fn client(&self) -> SupervisorClient {
+        self.client.clone()
+    }
+
+    pub async fn shutdown(self, timeout: Duration) -> JoinHandle<bool> {
+        let _ = self
+            .client
+            .send(SupervisorCommand::Shutdown { timeout })
+            .await;
+        self.task
+    }
+
+    pub async fn remove(self) {
+        drop(self.client);
+        let _ = self.task.await;
+    }
+}
+
+// A cheap, cloneable way to talk to a supervisor from other tasks.
+#[derive(Clone)]
+pub struct SupervisorClient {
+    commands: mpsc::Sender<SupervisorCommand>,
+}
+
+impl SupervisorClient {
+    pub async fn health(&self) -> Result<oneshot::Receiver<InstanceHealth>> {
+        let (reply, receiver) = oneshot::channel();
+        self.send(SupervisorCommand::Health { reply }).await?;
+        Ok(receiver)
+    }
+
+    pub async fn start(&self) -> Result<()> {
+        let (reply, receiver) = oneshot::channel();
+        self.send(SupervisorCommand::Start { reply }).await?;
+        receiver.await.map_err(|_| stopped_error())?
+    }
+
+    pub async fn stop(&self) -> Result<()> {
+        let (reply, receiver) = oneshot::channel();
+        self.send(SupervisorCommand::Stop { reply }).await?;
+        receiver.await.map_err(|_| stopped_error())?
+    }
+
+    pub async fn restart(&self) -> Result<()> {
+        let (reply, receiver) = oneshot::channel();
+        self.send(SupervisorCommand::Restart { reply }).await?;
+        receiver.await.map_err(|_| stopped_error())?
+    }
+
+    async fn send(&self, command: SupervisorCommand) -> Result<()> {
+        self.commands
+            .send(command)
+            .await
+            .map_err(|_| stopped_error())
+    }
+}
+
+fn stopped_error() -> anyhow::Error {
+    anyhow!("Instance supervisor is not running")
+}
+
+pub struct Supervisor {
+    process: NgrokProcess,
+    settings: watch::Receiver<SupervisorSettings>,
+    events: mpsc::UnboundedSender<InstanceEvent>,
+    known_tunnels: Option<Vec<NgrokTunnel>>,
+    last_health: Option<InstanceHealth>,
+    restart_state: RestartState,
+    stopped: bool,
+}
+
+impl Supervisor {
+    pub fn spawn(
+        process: NgrokProcess,
+        settings: watch::Receiver<SupervisorSettings>,
+        events: mpsc::UnboundedSender<InstanceEvent>,
+    ) -> SupervisorHandle {
+        let (commands, receiver) = mpsc::channel(8);
+        let supervisor = Self {
+            process,
+            settings,
+            events,
+            known_tunnels: None,
+            last_health: None,
+            restart_state: RestartState::default(),
+            stopped: false,
+        };
+        SupervisorHandle {
+            client: SupervisorClient { commands },
+            task: tokio::spawn(supervisor.run(receiver)),
+        }
+    }
+
+    async fn run(mut self, mut commands: mpsc::Receiver<SupervisorCommand>) -> bool {
+        let mut command = self.start_up(&mut commands).await;
+
+        let mut next_check = Instant::now() + self.settings.borrow().check_interval;
+        loop {
+            match command.take() {
+                Some(Some(SupervisorCommand::Shutdown { timeout })) => {
+                    return self.process.shutdown(timeout).await;
+                }
+                Some(Some(pending)) => self.handle_command(pending).await,
+                Some(None) => break,
+                None => {}
+            }
+
+            tokio::select! {
+                () = sleep_until(next_check) => {
+                    let delay = self.check().await;
+                    next_check = Instant::now() + delay;
+                }
+                received = commands.recv() => command = Some(received),
+            }
+        }
+
+        if let Err(e) = self.process.kill() {
+            error!("Failed to stop ngrok instance {}: {e}", self.name());
+        }
+        false
+    }
+
+    // A shutdown or a closed channel cancels the initial start, so they never
+    // wait for the instance to come up. Health queries are answered right away
+    // and other commands run once the start has finished.
+    async fn start_up(
+        &mut self,
+        commands: &mut mpsc::Receiver<SupervisorCommand>,
+    ) -> Option<Option<SupervisorCommand>> {
+        let mut queued = Vec::new();
+        {
+            let start = self.start();
+            tokio::pin!(start);
+            loop {
+                tokio::select! {
+                    () = &mut start => break,
+                    received = commands.recv() => match received {
+                        Some(SupervisorCommand::Health { reply }) => {
+                            let _ = reply.send(InstanceHealth::Degraded("starting up".to_string()));
+                        }
+                        Some(command @ SupervisorCommand::Shutdown { .. }) => {
+                            return Some(Some(command));
+                        }
+                        Some(command) => queued.push(command),
+                        None => return Some(None),
+                    },
+                }
+            }
+        }
+
+        for command in queued {
+            self.handle_command(command).await;
+        }
+        None
+    }
+
+    fn name(&self) -> String {
+        self.process.name().to_string()
+    }
+
+    fn emit(&self, kind: InstanceEventKind) {
+        let _ = self.events.send(InstanceEvent {
+            instance: self.name(),
+            kind,
+        });
+    }
+
+    async fn start(&mut self) {
+        let name = self.name();
+        match self.process.start(self.startup_timeout()).await {
+            Ok(tunnels) => {
+                info!(
+                    "Started ngrok instance: {name} ({} tunnel(s) online)",
+                    tunnels.len()
+                );
+                self.announce_tunnel_changes(tunnels);
+            }
+            Err(e) => {
+                error!("Failed to start ngrok instance {name}: {e}");
+                self.emit(InstanceEventKind::StartFailed(e.to_string()));
+            }
+        }
+    }
+
+    async fn restart(&mut self) -> Result<Vec<NgrokTunnel>> {
+        info!("Restarting ngrok instance: {}", self.name());
+        self.process.kill()?;
+        self.process.start(self.startup_timeout()).await
+    }
+
+    fn startup_timeout(&self) -> Duration {
+        self.settings.borrow().startup_timeout
+    }
+
+    async fn check(&mut self) -> Duration {
+        let settings = self.settings.borrow().clone();
+        let interval = settings.check_interval;
+        if self.stopped {
+            return interval;
+        }
+
+        let name = self.name();
+        let status = self.process.check_health().await;
+        // Only a change of state is announced; the reasons embed ngrok's latest
+        // log line, so comparing them would re-notify on every new wording.
+        let changed = self
+            .last_health
+            .as_ref()
+            .is_none_or(|last| discriminant(last) != discriminant(&status));
+        self.last_health = Some(status.clone());
+
+        let reason = match status {
+            InstanceHealth::Healthy => {
+                if changed {
+                    info!("Instance '{name}' is healthy");
+                }
+                if self
+                    .restart_state
+                    .record_healthy(&settings.restart_policy, Instant::now())
+                {
+                    info!("Instance '{name}' is stable again, resetting its restart budget");
+                }
+                self.refresh_tunnels().await;
+                return interval;
+            }
+            InstanceHealth::Degraded(reason) => {
+                self.restart_state.record_unhealthy();
+                warn!("Instance '{name}' is degraded: {reason}");
+                if changed {
+                    self.emit(InstanceEventKind::Degraded(reason));
+                }
+                return interval;
+            }
+            InstanceHealth::Dead(reason) => reason,
+        };
+
+        if !settings.auto_restart {
+            error!("Instance '{name}' is not healthy: {reason}");
+            if changed {
+                self.emit(InstanceEventKind::Down(reason));
+            }
+            return interval;
+        }
+
+        match self
+            .restart_state
+            .decide(&settings.restart_policy, Instant::now())
+        {
+            RestartDecision::Restart => {
+                error!("Instance '{name}' is not healthy: {reason}");
+            }
+            RestartDecision::Wait(delay) => {
+                warn!(
+                    "Instance '{name}' is not healthy: {reason}; next restart in {}s",
+                    delay.as_secs()
+                );
+                return delay.min(interval);
+            }
+            RestartDecision::GiveUp(restarts) => {
+                error!(
+                    "Giving up on instance '{name}' after {restarts} restarts within {}s: {reason}",
+                    settings.restart_policy.window().as_secs()
+                );
+                self.emit(InstanceEventKind::GaveUp { restarts, reason });
+                return interval;
+            }
+            RestartDecision::GaveUp => return interval,
+        }
+
+        warn!("Auto-restarting instance '{name}'");
+        self.emit(InstanceEventKind::Restarting(reason));
+
+        let result = self.restart().await;
+        self.restart_state
+            .record_restart(&settings.restart_policy, Instant::now());
+        match result {
+            Ok(tunnels) => {
+                info!("Successfully restarted instance '{name}'");
+                self.announce_tunnel_changes(tunnels);
+            }
+            Err(e) => {
+                error!("Failed to restart instance '{name}': {e}");
+                self.emit(InstanceEventKind::RestartFailed(e.to_string()));
+            }
+        }
+        interval
+    }
+
+    async fn handle_command(&mut self, command: SupervisorCommand) {
+        match command {
+            SupervisorCommand::Health { reply } => {
+                let _ = reply.send(self.process.check_health().await);
+            }
+            SupervisorCommand::Start { reply } => {
+                let result = self.process.start(self.startup_timeout()).await;
+                if result.is_ok() {
+                    info!("Started ngrok instance: {}", self.name());
+                    self.resume();
+                }
+                let _ = reply.send(result.map(drop));
+            }
+            SupervisorCommand::Stop { reply } => {
+                let result = self.process.kill();
+                if result.is_ok() {
+                    info!("Stopped ngrok instance: {}", self.name());
+                    self.stopped = true;
+                    self.last_health = None;
+                    self.emit(InstanceEventKind::Stopped);
+                }
+                let _ = reply.send(result);
+            }
+            SupervisorCommand::Restart { reply } => {
+                let result = self.restart().await;
+                if result.is_ok() {
+                    self.resume();
+                }
+                let _ = reply.send(result.map(drop));
+            }
+            SupervisorCommand::Shutdown { .. } => {}
+        }
+    }
+
+    fn resume(&mut self) {
+        self.stopped = false;
+        self.last_health = None;
+        self.restart_state = RestartState::default();
+    }
+
+    async fn refresh_tunnels(&mut self) {
+        match self.process.tunnels().await {
+            Ok(tunnels) => self.announce_tunnel_changes(tunnels),
+            Err(e) => error!("Failed to fetch tunnels for '{}': {e}", self.name()),
+        }
+    }
+
+    fn announce_tunnel_changes(&mut self, tunnels: Vec<NgrokTunnel>) {
+        if tunnels.is_empty() {
+            return;
+        }
+
+        match self.known_tunnels.take() {
+            None => {
+                self.emit(InstanceEventKind::TunnelsReady(tunnels.clone()));
+            }
+            Some(previous) if public_urls(&previous) != public_urls(&tunnels) => {
+                info!("Tunnel URLs for '{}' changed", self.name());
+                self.emit(InstanceEventKind::TunnelsChanged {
+                    previous,
+                    current: tunnels.clone(),
+                });
+            }
+            Some(_) => {}
+        }
+
+        self.known_tunnels = Some(tunnels);
+    }
+}
+
+fn public_urls(tunnels: &[NgrokTunnel]) -> Vec<&str> {
+    let mut urls: Vec<&str> = tunnels.iter().map(|t| t.public_url.as_str()).collect();
+    urls.sort_unstable();
+    urls
+}
@@ -1,7 +1,16 @@
+use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+// The agent API is local, so anything slower than this means ngrok is stuck.
+const API_TIMEOUT: Duration = Duration::from_secs(5);
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NgrokTunnel {
+    #[serde(default)]
+    pub name: String,
     pub public_url: String,
     pub proto: String,
     pub config: NgrokTunnelConfig,
@@ -11,3 +20,33 @@ pub struct NgrokTunnel {
 pub struct NgrokTunnelConfig {
     pub addr: String,
 }
+
+#[derive(Deserialize)]
+struct TunnelsResponse {
+    tunnels: Vec<NgrokTunnel>,
+}
+
+fn client() -> &'static reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    CLIENT.get_or_init(|| {
+        reqwest::Client::builder()
+            .timeout(API_TIMEOUT)
+            .build()
+            .unwrap_or_default()
+    })
+}
+
+pub async fn fetch(web_addr: SocketAddr) -> Result<Vec<NgrokTunnel>> {
+    let response = client()
+        .get(format!("http://{web_addr}/api/tunnels"))
+        .send()
+        .await
+        .with_context(|| format!("Failed to query ngrok API at {web_addr}"))?;
+
+    let status: TunnelsResponse = response
+        .json()
+        .await
+        .context("Failed to parse ngrok API response")?;
+
+    Ok(status.tunnels)
+}
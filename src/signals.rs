@@ -0,0 +1,56 @@
+use anyhow::{Context, Result};
+use tokio::signal;
+#[cfg(unix)]
+use tokio::signal::unix::{Signal, SignalKind, signal as unix_signal};
+
+pub enum ControlSignal {
+    Shutdown(&'static str),
+    #[cfg_attr(not(unix), allow(dead_code))]
+    Reload,
+}
+
+// SIGTERM and SIGHUP only exist on Unix; elsewhere LinkUp stops on Ctrl+C and
+// has no reload signal.
+pub struct SignalListener {
+    #[cfg(unix)]
+    sigterm: Signal,
+    #[cfg(unix)]
+    sighup: Signal,
+}
+
+impl SignalListener {
+    #[cfg(unix)]
+    pub fn new() -> Result<Self> {
+        Ok(Self {
+            sigterm: unix_signal(SignalKind::terminate())
+                .context("Failed to install SIGTERM handler")?,
+            sighup: unix_signal(SignalKind::hangup())
+                .context("Failed to install SIGHUP handler")?,
+        })
+    }
+
+    #[cfg(not(unix))]
+    pub fn new() -> Result<Self> {
+        Ok(Self {})
+    }
+
+    #[cfg(unix)]
+    pub async fn next(&mut self) -> Result<ControlSignal> {
+        tokio::select! {
+            result = signal::ctrl_c() => {
+                result.context("Failed to listen for SIGINT")?;
+                Ok(ControlSignal::Shutdown("SIGINT"))
+            }
+            _ = self.sigterm.recv() => Ok(ControlSignal::Shutdown("SIGTERM")),
+            _ = self.sighup.recv() => Ok(ControlSignal::Reload),
+        }
+    }
+
+    #[cfg(not(unix))]
+    pub async fn next(&mut self) -> Result<ControlSignal> {
+        signal::ctrl_c()
+            .await
+            .context("Failed to listen for Ctrl+C")?;
+        Ok(ControlSignal::Shutdown("Ctrl+C"))
+    }
+}
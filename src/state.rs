@@ -0,0 +1,216 @@
+use anyhow::{Context, Result};
+use chrono::Utc;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::ngrok::NgrokTunnel;
+use crate::webhook::NotificationEvent;
+
+const STATE_FILE: &str = "state.json";
+const MAX_HISTORY: usize = 50;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct State {
+    #[serde(default)]
+    instances: BTreeMap<String, InstanceState>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct InstanceState {
+    #[serde(default)]
+    tunnels: Vec<TunnelRecord>,
+    #[serde(default)]
+    history: Vec<TunnelRecord>,
+    #[serde(default)]
+    restarts: u64,
+    #[serde(default)]
+    last_error: Option<String>,
+    #[serde(default)]
+    last_error_at: Option<String>,
+    #[serde(default)]
+    stopped_at: Option<String>,
+    #[serde(default)]
+    updated_at: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct TunnelRecord {
+    name: String,
+    public_url: String,
+    proto: String,
+    addr: String,
+    active_since: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    active_until: Option<String>,
+}
+
+// Remembers each instance's tunnel URLs, restarts and last error in
+// `state_dir/state.json`, so a restarted LinkUp doesn't re-announce URLs that
+// haven't changed.
+pub struct StateStore {
+    path: Option<PathBuf>,
+    state: State,
+    restored: HashSet<String>,
+}
+
+impl StateStore {
+    pub fn load(state_dir: Option<&Path>) -> Result<Self> {
+        let Some(state_dir) = state_dir else {
+            return Ok(Self {
+                path: None,
+                state: State::default(),
+                restored: HashSet::new(),
+            });
+        };
+
+        fs::create_dir_all(state_dir)
+            .with_context(|| format!("Failed to create state directory {}", state_dir.display()))?;
+        let path = state_dir.join(STATE_FILE);
+        // The state only saves notifications, so a broken file must not keep
+        // LinkUp from starting.
+        let state: State = match fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| set_aside(&path, &e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => State::default(),
+            Err(e) => set_aside(&path, &e),
+        };
+        info!(
+            "Loaded state for {} instance(s) from {}",
+            state.instances.len(),
+            path.display()
+        );
+
+        Ok(Self {
+            path: Some(path),
+            restored: state
+                .instances
+                .iter()
+                .filter(|(_, instance)| !instance.tunnels.is_empty())
+                .map(|(name, _)| name.clone())
+                .collect(),
+            state,
+        })
+    }
+
+    // Records the event and returns whether it should still be sent. The first
+    // "tunnels ready" of an instance is dropped when its URLs match the ones
+    // saved by the previous run.
+    pub fn record(&mut self, event: &NotificationEvent) -> bool {
+        let now = Utc::now().to_rfc3339();
+        let instance = self
+            .state
+            .instances
+            .entry(event.instance().to_string())
+            .or_default();
+
+        let mut notify = true;
+        match event {
+            NotificationEvent::TunnelsReady { tunnels, .. } => {
+                let restored = self.restored.remove(event.instance());
+                notify = !(restored && instance.has_urls(tunnels));
+                instance.set_tunnels(tunnels, &now);
+                instance.stopped_at = None;
+            }
+            NotificationEvent::TunnelUrlChanged { current, .. } => {
+                instance.set_tunnels(current, &now);
+            }
+            NotificationEvent::InstanceError { error, .. } => {
+                instance.set_error(error, &now);
+            }
+            NotificationEvent::Restarting { .. } => instance.restarts += 1,
+            NotificationEvent::GaveUp { reason, .. } => instance.set_error(reason, &now),
+            NotificationEvent::Stopped { .. } => instance.stopped_at = Some(now.clone()),
+        }
+        instance.updated_at = Some(now);
+
+        self.save();
+        notify
+    }
+
+    fn save(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        // Write to a temporary file first so a crash never leaves a truncated state file.
+        let temp_path = path.with_extension("json.tmp");
+        let result = serde_json::to_vec_pretty(&self.state)
+            .map_err(anyhow::Error::from)
+            .and_then(|content| Ok(fs::write(&temp_path, content)?))
+            .and_then(|()| Ok(fs::rename(&temp_path, path)?));
+        if let Err(e) = result {
+            warn!("Failed to write state file {}: {e}", path.display());
+        }
+    }
+}
+
+// Moves an unreadable state file out of the way, keeping it for inspection,
+// and starts over with an empty state.
+fn set_aside(path: &Path, error: &dyn std::fmt::Display) -> State {
+    let corrupt_path = path.with_extension("json.corrupt");
+    warn!(
+        "Failed to load state file {}: {error}; starting with an empty state",
+        path.display()
+    );
+    match fs::rename(path, &corrupt_path) {
+        Ok(()) => warn!("Moved the state file to {}", corrupt_path.display()),
+        Err(e) => warn!(
+            "Failed to move the state file to {}: {e}",
+            corrupt_path.display()
+        ),
+    }
+    State::default()
+}
+
+impl InstanceState {
+    fn has_urls(&self, tunnels: &[NgrokTunnel]) -> bool {
+        let mut saved: Vec<(&str, &str)> = self
+            .tunnels
+            .iter()
+            .map(|t| (t.name.as_str(), t.public_url.as_str()))
+            .collect();
+        let mut current: Vec<(&str, &str)> = tunnels
+            .iter()
+            .map(|t| (t.name.as_str(), t.public_url.as_str()))
+            .collect();
+        saved.sort_unstable();
+        current.sort_unstable();
+        saved == current
+    }
+
+    fn set_tunnels(&mut self, tunnels: &[NgrokTunnel], now: &str) {
+        let previous = std::mem::take(&mut self.tunnels);
+        for tunnel in tunnels {
+            let kept = previous
+                .iter()
+                .find(|old| old.name == tunnel.name && old.public_url == tunnel.public_url);
+            self.tunnels.push(TunnelRecord {
+                name: tunnel.name.clone(),
+                public_url: tunnel.public_url.clone(),
+                proto: tunnel.proto.clone(),
+                addr: tunnel.config.addr.clone(),
+                active_since: kept.map_or_else(|| now.to_string(), |old| old.active_since.clone()),
+                active_until: None,
+            });
+        }
+
+        for mut old in previous {
+            if !self
+                .tunnels
+                .iter()
+                .any(|t| t.name == old.name && t.public_url == old.public_url)
+            {
+                old.active_until = Some(now.to_string());
+                self.history.push(old);
+            }
+        }
+        let excess = self.history.len().saturating_sub(MAX_HISTORY);
+        self.history.drain(..excess);
+    }
+
+    fn set_error(&mut self, error: &str, now: &str) {
+        self.last_error = Some(error.to_string());
+        self.last_error_at = Some(now.to_string());
+    }
+}
@@ -0,0 +1,104 @@
+use anyhow::{Context, Result};
+use log::warn;
+use reqwest::header::RETRY_AFTER;
+use reqwest::{Client, RequestBuilder, Response, StatusCode};
+use serde_json::Value;
+use std::time::Duration;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_ATTEMPTS: u32 = 5;
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(300);
+
+pub fn client() -> Client {
+    Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .expect("Failed to build HTTP client")
+}
+
+// Sends the request, retrying network failures, 429s and 5xx responses with
+// exponential backoff. Returns the status and body of the final response.
+pub async fn send(label: &str, request: RequestBuilder) -> Result<(StatusCode, String)> {
+    send_with(label, || {
+        request
+            .try_clone()
+            .context("Webhook request body cannot be retried")
+    })
+    .await
+}
+
+// Like `send`, but builds a fresh request for every attempt, e.g. to sign it
+// with the current time.
+pub async fn send_with(
+    label: &str,
+    build: impl Fn() -> Result<RequestBuilder>,
+) -> Result<(StatusCode, String)> {
+    let mut attempt = 1;
+    loop {
+        let (delay, reason) = match build()?.send().await {
+            Ok(response) => {
+                let status = response.status();
+                let header_delay = retry_after_header(&response);
+                let body = response.text().await.unwrap_or_default();
+                if !is_retryable(status) || attempt >= MAX_ATTEMPTS {
+                    return Ok((status, body));
+                }
+                let delay = header_delay
+                    .or_else(|| retry_after_body(&body))
+                    .map_or_else(|| backoff(attempt), |delay| delay.min(MAX_RETRY_AFTER));
+                (delay, format!("status {status}"))
+            }
+            // The URL may carry a token, so leave it out of errors and logs.
+            Err(e) if e.is_builder() || attempt >= MAX_ATTEMPTS => {
+                return Err(e.without_url().into());
+            }
+            Err(e) => (backoff(attempt), e.without_url().to_string()),
+        };
+
+        warn!(
+            "{label} delivery failed ({reason}), retrying in {:.1}s (attempt {attempt}/{MAX_ATTEMPTS})",
+            delay.as_secs_f64()
+        );
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+fn is_retryable(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS
+        || status == StatusCode::REQUEST_TIMEOUT
+        || status.is_server_error()
+}
+
+fn retry_after_header(response: &Response) -> Option<Duration> {
+    let seconds = response
+        .headers()
+        .get(RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse::<f64>()
+        .ok()?;
+    Duration::try_from_secs_f64(seconds).ok()
+}
+
+// Discord sends `retry_after` at the top level, Telegram under `parameters`.
+fn retry_after_body(body: &str) -> Option<Duration> {
+    let value: Value = serde_json::from_str(body).ok()?;
+    let seconds = value
+        .get("retry_after")
+        .or_else(|| value.pointer("/parameters/retry_after"))?
+        .as_f64()?;
+    Duration::try_from_secs_f64(seconds).ok()
+}
+
+fn backoff(attempt: u32) -> Duration {
+    let exponent = attempt.saturating_sub(1).min(16);
+    let base = INITIAL_BACKOFF
+        .saturating_mul(1 << exponent)
+        .min(MAX_BACKOFF);
+    let half = base / 2;
+    half + half.mul_f64(fastrand::f64())
+}
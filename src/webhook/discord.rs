@@ -1,9 +1,11 @@
 use anyhow::{Context, Result};
 use chrono::Utc;
 use reqwest::Client;
-use serde_json::json;
+use serde_json::{Value, json};
 
-const SUCCESS_COLOR: u32 = 3_066_993;
+use super::delivery;
+use super::event::{NotificationEvent, display_url};
+use super::template::MessageTemplates;
 
 pub struct DiscordWebhook {
     client: Client,
@@ -12,51 +14,37 @@ pub struct DiscordWebhook {
 impl DiscordWebhook {
     pub fn new() -> Self {
         Self {
-            client: Client::new(),
+            client: delivery::client(),
         }
     }
 
-    pub async fn send_message(
+    pub async fn send(
         &self,
         url: &str,
-        title: &str,
-        description: &str,
-        color: u32,
+        event: &NotificationEvent,
+        templates: &MessageTemplates,
     ) -> Result<()> {
-        let timestamp = Utc::now().to_rfc3339();
+        let (description, fields) = match templates.body(event, str::to_owned) {
+            Some(body) => (body, Vec::new()),
+            None => (
+                format!("**{}**\n\n{}", event.instance(), event.summary()),
+                tunnel_fields(event),
+            ),
+        };
 
         let payload = json!({
             "embeds": [{
-                "title": title,
+                "title": templates
+                    .title(event, str::to_owned)
+                    .unwrap_or_else(|| event.title().to_string()),
                 "description": description,
-                "color": color,
-                "timestamp": timestamp,
-                "footer": {
-                    "text": "LinkUp Ngrok Manager",
-                }
-            }]
-        });
-
-        self.post_payload(url, payload).await
-    }
-
-    pub async fn send_tunnels(
-        &self,
-        url: &str,
-        instance_name: &str,
-        fields: Vec<serde_json::Value>,
-    ) -> Result<()> {
-        let timestamp = Utc::now().to_rfc3339();
-
-        let payload = json!({
-            "embeds": [{
-                "title": "Ngrok Tunnels are Ready!",
-                "description": format!("**{instance_name}**\n\nTunnels are ready and accessible:"),
-                "color": SUCCESS_COLOR,
+                "color": event.color(),
                 "fields": fields,
-                "timestamp": timestamp,
+                "timestamp": Utc::now().to_rfc3339(),
                 "footer": {
-                    "text": "LinkUp Ngrok Manager",
+                    "text": templates
+                        .footer(event, str::to_owned)
+                        .unwrap_or_else(|| "LinkUp Ngrok Manager".to_string()),
                 }
             }]
         });
@@ -64,18 +52,13 @@ impl DiscordWebhook {
         self.post_payload(url, payload).await
     }
 
-    async fn post_payload(&self, url: &str, payload: serde_json::Value) -> Result<()> {
-        let response = self
-            .client
-            .post(url)
-            .json(&payload)
-            .send()
+    async fn post_payload(&self, url: &str, payload: Value) -> Result<()> {
+        let request = self.client.post(url).json(&payload);
+        let (status, body) = delivery::send("Discord webhook", request)
             .await
             .context("Failed to send Discord webhook")?;
 
-        if !response.status().is_success() {
-            let status = response.status();
-            let body = response.text().await.unwrap_or_default();
+        if !status.is_success() {
             return Err(anyhow::anyhow!(
                 "Discord webhook failed with status {status}: {body}"
             ));
@@ -84,3 +67,23 @@ impl DiscordWebhook {
         Ok(())
     }
 }
+
+fn tunnel_fields(event: &NotificationEvent) -> Vec<Value> {
+    event
+        .tunnel_entries()
+        .iter()
+        .map(|entry| {
+            let tunnel = entry.tunnel;
+            let current = format!("```\n{}\n```", display_url(&tunnel.public_url));
+            let value = match entry.previous_url {
+                Some(previous_url) => format!("~~{}~~\n{current}", display_url(previous_url)),
+                None => current,
+            };
+            json!({
+                "name": format!("🔗 {} → {}", tunnel.proto.to_uppercase(), tunnel.config.addr),
+                "value": value,
+                "inline": false,
+            })
+        })
+        .collect()
+}
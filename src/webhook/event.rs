@@ -0,0 +1,212 @@
+use std::fmt::Write;
+
+use crate::ngrok::NgrokTunnel;
+
+pub const COLOR_READY: u32 = 3_066_993;
+pub const COLOR_CHANGED: u32 = 3_447_003;
+pub const COLOR_ERROR: u32 = 15_158_332;
+pub const COLOR_RESTARTING: u32 = 16_776_960;
+pub const COLOR_STOPPED: u32 = 9_807_270;
+
+pub const EVENT_KINDS: [&str; 6] = [
+    "tunnels_ready",
+    "tunnel_url_changed",
+    "instance_error",
+    "restarting",
+    "gave_up",
+    "stopped",
+];
+
+#[derive(Debug, Clone)]
+pub enum NotificationEvent {
+    TunnelsReady {
+        instance: String,
+        tunnels: Vec<NgrokTunnel>,
+    },
+    TunnelUrlChanged {
+        instance: String,
+        previous: Vec<NgrokTunnel>,
+        current: Vec<NgrokTunnel>,
+    },
+    InstanceError {
+        instance: String,
+        error: String,
+    },
+    Restarting {
+        instance: String,
+        reason: String,
+    },
+    GaveUp {
+        instance: String,
+        restarts: usize,
+        reason: String,
+    },
+    Stopped {
+        instance: String,
+    },
+}
+
+pub struct TunnelEntry<'a> {
+    pub tunnel: &'a NgrokTunnel,
+    pub previous_url: Option<&'a str>,
+}
+
+impl NotificationEvent {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::TunnelsReady { .. } => "tunnels_ready",
+            Self::TunnelUrlChanged { .. } => "tunnel_url_changed",
+            Self::InstanceError { .. } => "instance_error",
+            Self::Restarting { .. } => "restarting",
+            Self::GaveUp { .. } => "gave_up",
+            Self::Stopped { .. } => "stopped",
+        }
+    }
+
+    pub fn instance(&self) -> &str {
+        match self {
+            Self::TunnelsReady { instance, .. }
+            | Self::TunnelUrlChanged { instance, .. }
+            | Self::InstanceError { instance, .. }
+            | Self::Restarting { instance, .. }
+            | Self::GaveUp { instance, .. }
+            | Self::Stopped { instance } => instance,
+        }
+    }
+
+    pub fn tunnels(&self) -> &[NgrokTunnel] {
+        match self {
+            Self::TunnelsReady { tunnels, .. } => tunnels,
+            Self::TunnelUrlChanged { current, .. } => current,
+            _ => &[],
+        }
+    }
+
+    pub fn error(&self) -> Option<&str> {
+        match self {
+            Self::InstanceError { error, .. } => Some(error),
+            Self::Restarting { reason, .. } | Self::GaveUp { reason, .. } => Some(reason),
+            _ => None,
+        }
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            Self::TunnelsReady { .. } => "🚀 Ngrok Tunnels are Ready!",
+            Self::TunnelUrlChanged { .. } => "🔀 Ngrok Tunnel URL Changed",
+            Self::InstanceError { .. } => "❌ Error",
+            Self::Restarting { .. } => "🔄 Restarting",
+            Self::GaveUp { .. } => "⛔ Gave Up",
+            Self::Stopped { .. } => "🛑 Stopped",
+        }
+    }
+
+    pub fn color(&self) -> u32 {
+        match self {
+            Self::TunnelsReady { .. } => COLOR_READY,
+            Self::TunnelUrlChanged { .. } => COLOR_CHANGED,
+            Self::InstanceError { .. } | Self::GaveUp { .. } => COLOR_ERROR,
+            Self::Restarting { .. } => COLOR_RESTARTING,
+            Self::Stopped { .. } => COLOR_STOPPED,
+        }
+    }
+
+    pub fn summary(&self) -> String {
+        match self {
+            Self::TunnelsReady { .. } => "Tunnels are ready and accessible:".to_string(),
+            Self::TunnelUrlChanged { .. } => "Tunnels are now reachable at new URLs:".to_string(),
+            Self::InstanceError { error, .. } => error.clone(),
+            Self::Restarting { reason, .. } => format!("Restarting ngrok instance - {reason}"),
+            Self::GaveUp {
+                restarts, reason, ..
+            } => format!("Gave up restarting after {restarts} restarts - {reason}"),
+            Self::Stopped { .. } => "Ngrok instance stopped".to_string(),
+        }
+    }
+
+    pub fn message(&self) -> String {
+        let instance = self.instance();
+        match self {
+            Self::TunnelsReady { .. } => {
+                format!(
+                    "Ngrok tunnels for '{instance}' are ready:\n{}",
+                    self.tunnel_lines()
+                )
+            }
+            Self::TunnelUrlChanged { .. } => {
+                format!(
+                    "Ngrok tunnel URLs for '{instance}' changed:\n{}",
+                    self.tunnel_lines()
+                )
+            }
+            Self::InstanceError { error, .. } => {
+                format!("❌ LinkUp Error: Instance '{instance}' - {error}")
+            }
+            Self::Restarting { reason, .. } => {
+                format!("🔄 LinkUp: Restarting ngrok instance '{instance}' - {reason}")
+            }
+            Self::GaveUp {
+                restarts, reason, ..
+            } => format!(
+                "⛔ LinkUp: Gave up restarting ngrok instance '{instance}' after {restarts} restarts - {reason}"
+            ),
+            Self::Stopped { .. } => format!("🛑 LinkUp: Ngrok instance '{instance}' stopped"),
+        }
+    }
+
+    fn tunnel_lines(&self) -> String {
+        let mut lines = String::new();
+        for entry in self.tunnel_entries() {
+            let tunnel = entry.tunnel;
+            let _ = match entry.previous_url {
+                Some(previous_url) => writeln!(
+                    lines,
+                    "• {previous_url} → {} ({})",
+                    tunnel.public_url, tunnel.config.addr
+                ),
+                None => writeln!(lines, "• {} → {}", tunnel.public_url, tunnel.config.addr),
+            };
+        }
+        lines
+    }
+
+    pub fn tunnel_entries(&self) -> Vec<TunnelEntry<'_>> {
+        match self {
+            Self::TunnelsReady { tunnels, .. } => tunnels
+                .iter()
+                .map(|tunnel| TunnelEntry {
+                    tunnel,
+                    previous_url: None,
+                })
+                .collect(),
+            Self::TunnelUrlChanged {
+                previous, current, ..
+            } => current
+                .iter()
+                .filter_map(|tunnel| {
+                    // Tunnels are named in the agent config; older agents may
+                    // leave the name out, so fall back to addr and proto.
+                    let previous_url = previous
+                        .iter()
+                        .find(|old| {
+                            if tunnel.name.is_empty() {
+                                old.config.addr == tunnel.config.addr && old.proto == tunnel.proto
+                            } else {
+                                old.name == tunnel.name
+                            }
+                        })
+                        .map_or("(none)", |old| old.public_url.as_str());
+                    (previous_url != tunnel.public_url).then_some(TunnelEntry {
+                        tunnel,
+                        previous_url: Some(previous_url),
+                    })
+                })
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+pub fn display_url(url: &str) -> &str {
+    url.split_once("://").map_or(url, |(_, rest)| rest)
+}
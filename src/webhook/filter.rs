@@ -0,0 +1,82 @@
+use super::event::NotificationEvent;
+use crate::config::Webhook;
+
+// Decides which notifications a webhook receives. An empty list lets everything through.
+pub struct WebhookFilter {
+    events: Vec<String>,
+    instances: Vec<String>,
+}
+
+impl WebhookFilter {
+    pub fn from_webhook(webhook: &Webhook) -> Self {
+        Self {
+            events: webhook.events.clone(),
+            instances: webhook.instances.clone(),
+        }
+    }
+
+    pub fn allows(&self, event: &NotificationEvent) -> bool {
+        matches_any(&self.events, event.kind()) && matches_any(&self.instances, event.instance())
+    }
+}
+
+fn matches_any(patterns: &[String], value: &str) -> bool {
+    patterns.is_empty() || patterns.iter().any(|pattern| glob_match(pattern, value))
+}
+
+// Matches `*` (any run of characters) and `?` (a single character).
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                // Let the last `*` swallow one more character and try again.
+                Some((star, matched)) => {
+                    p = star + 1;
+                    t = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_literals_and_wildcards() {
+        assert!(glob_match("stopped", "stopped"));
+        assert!(!glob_match("stopped", "stopped2"));
+        assert!(glob_match("dev-?", "dev-1"));
+        assert!(!glob_match("dev-?", "dev-12"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("tunnel*", "tunnel_url_changed"));
+        assert!(!glob_match("tunnel?", "tunnel"));
+    }
+
+    #[test]
+    fn backtracks_over_stars() {
+        assert!(glob_match("*_ready", "tunnels_ready_ready"));
+        assert!(glob_match("a*b*c", "axxbyybzzc"));
+        assert!(glob_match("*ab?d*", "abxabcd-1"));
+        assert!(!glob_match("a*b*c", "axxbyyb"));
+        assert!(!glob_match("*_error", "instance_errors"));
+    }
+}
@@ -1,7 +1,53 @@
 use anyhow::{Context, Result};
 use chrono::Utc;
-use reqwest::Client;
-use serde_json::json;
+use reqwest::header::HeaderValue;
+use reqwest::{Client, Method, RequestBuilder};
+use serde::Serialize;
+use serde_json::Value;
+
+use super::delivery;
+use super::event::NotificationEvent;
+use super::signing;
+use super::template::MessageTemplates;
+use crate::config::{ContentType, HttpMethod, Webhook};
+use crate::ngrok::NgrokTunnel;
+
+#[derive(Serialize)]
+struct Payload<'a> {
+    event: &'static str,
+    instance: &'a str,
+    tunnels: Vec<TunnelPayload<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    previous_tunnels: Option<Vec<TunnelPayload<'a>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    restarts: Option<usize>,
+    error: Option<&'a str>,
+    message: String,
+    timestamp: String,
+    service: &'static str,
+}
+
+#[derive(Serialize)]
+struct TunnelPayload<'a> {
+    name: &'a str,
+    public_url: &'a str,
+    proto: &'a str,
+    addr: &'a str,
+}
+
+impl<'a> TunnelPayload<'a> {
+    fn list(tunnels: &'a [NgrokTunnel]) -> Vec<Self> {
+        tunnels
+            .iter()
+            .map(|tunnel| Self {
+                name: &tunnel.name,
+                public_url: &tunnel.public_url,
+                proto: &tunnel.proto,
+                addr: &tunnel.config.addr,
+            })
+            .collect()
+    }
+}
 
 pub struct GenericWebhook {
     client: Client,
@@ -10,28 +56,66 @@ pub struct GenericWebhook {
 impl GenericWebhook {
     pub fn new() -> Self {
         Self {
-            client: Client::new(),
+            client: delivery::client(),
         }
     }
 
-    pub async fn send_message(&self, url: &str, message: &str) -> Result<()> {
-        let payload = json!({
-            "message": message,
-            "timestamp": Utc::now().to_rfc3339(),
-            "service": "LinkUp"
-        });
-
-        let response = self
-            .client
-            .post(url)
-            .json(&payload)
-            .send()
-            .await
-            .context("Failed to send generic webhook")?;
-
-        if !response.status().is_success() {
-            let status = response.status();
-            let body = response.text().await.unwrap_or_default();
+    pub async fn send(
+        &self,
+        webhook: &Webhook,
+        event: &NotificationEvent,
+        templates: &MessageTemplates,
+    ) -> Result<()> {
+        let payload = match templates.payload(event) {
+            Some(payload) => payload,
+            None => serde_json::to_value(Payload {
+                event: event.kind(),
+                instance: event.instance(),
+                tunnels: TunnelPayload::list(event.tunnels()),
+                previous_tunnels: match event {
+                    NotificationEvent::TunnelUrlChanged { previous, .. } => {
+                        Some(TunnelPayload::list(previous))
+                    }
+                    _ => None,
+                },
+                restarts: match event {
+                    NotificationEvent::GaveUp { restarts, .. } => Some(*restarts),
+                    _ => None,
+                },
+                error: event.error(),
+                message: templates
+                    .body(event, str::to_owned)
+                    .unwrap_or_else(|| event.message()),
+                timestamp: Utc::now().to_rfc3339(),
+                service: "LinkUp",
+            })?,
+        };
+
+        let method = match webhook.method.unwrap_or_default() {
+            // Unknown methods are rejected when the config is loaded.
+            HttpMethod::Post | HttpMethod::Unknown => Method::POST,
+            HttpMethod::Put => Method::PUT,
+            HttpMethod::Patch => Method::PATCH,
+        };
+        let mut request = self.client.request(method, &webhook.url);
+        for (name, value) in &webhook.headers {
+            request = request.header(name, value);
+        }
+        let request = match webhook.content_type.unwrap_or_default() {
+            ContentType::Json | ContentType::Unknown => request.json(&payload),
+            ContentType::Form => request.form(&form_fields(&payload)),
+        };
+        let delivery_id = signing::delivery_id();
+        let (status, body) = delivery::send_with("Generic webhook", || {
+            let attempt = request
+                .try_clone()
+                .context("Generic webhook body cannot be retried")?;
+            self.sign(webhook, &delivery_id, attempt)
+        })
+        .await
+        .context("Failed to send generic webhook")?;
+
+        if !status.is_success() {
             return Err(anyhow::anyhow!(
                 "Generic webhook failed with status {status}: {body}"
             ));
@@ -39,4 +123,53 @@ impl GenericWebhook {
 
         Ok(())
     }
+
+    // Adds the delivery ID and, with a secret configured, the timestamp and
+    // HMAC signature headers. Every attempt is signed with the current time so
+    // late retries stay fresh, while the delivery ID lets receivers dedupe.
+    fn sign(
+        &self,
+        webhook: &Webhook,
+        delivery_id: &str,
+        request: RequestBuilder,
+    ) -> Result<RequestBuilder> {
+        let mut request = request
+            .build()
+            .context("Failed to build generic webhook request")?;
+        let mut headers = vec![(signing::DELIVERY_HEADER, delivery_id.to_string())];
+        if !webhook.secret.is_empty() {
+            let timestamp = Utc::now().timestamp();
+            let body = request
+                .body()
+                .and_then(|body| body.as_bytes())
+                .unwrap_or_default();
+            let signature = signing::sign(&webhook.secret, timestamp, delivery_id, body)?;
+            headers.push((signing::TIMESTAMP_HEADER, timestamp.to_string()));
+            headers.push((signing::SIGNATURE_HEADER, signature));
+        }
+        for (name, value) in headers {
+            request
+                .headers_mut()
+                .insert(name, HeaderValue::from_str(&value)?);
+        }
+        Ok(RequestBuilder::from_parts(self.client.clone(), request))
+    }
+}
+
+// Form bodies are flat, so nested values are sent as JSON text.
+fn form_fields(payload: &Value) -> Vec<(String, String)> {
+    let Value::Object(fields) = payload else {
+        return Vec::new();
+    };
+    fields
+        .iter()
+        .map(|(key, value)| {
+            let value = match value {
+                Value::String(text) => text.clone(),
+                Value::Null => String::new(),
+                other => other.to_string(),
+            };
+            (key.clone(), value)
+        })
+        .collect()
 }
@@ -0,0 +1,132 @@
+use anyhow::{Context, Result};
+use chrono::Utc;
+use reqwest::Client;
+use serde_json::{Value, json};
+
+use super::delivery;
+use super::event::{NotificationEvent, display_url};
+use super::template::MessageTemplates;
+use crate::ngrok::NgrokTunnel;
+
+pub struct GoogleChatWebhook {
+    client: Client,
+}
+
+impl GoogleChatWebhook {
+    pub fn new() -> Self {
+        Self {
+            client: delivery::client(),
+        }
+    }
+
+    pub async fn send(
+        &self,
+        url: &str,
+        event: &NotificationEvent,
+        templates: &MessageTemplates,
+    ) -> Result<()> {
+        let widgets = match templates.body(event, escape) {
+            Some(body) => vec![paragraph(&body)],
+            None => {
+                let mut widgets = vec![paragraph(&format!(
+                    "<font color=\"#{:06X}\"><b>{}</b></font><br>{}",
+                    event.color(),
+                    escape(event.instance()),
+                    escape(&event.summary())
+                ))];
+                widgets.extend(event.tunnel_entries().iter().map(|entry| {
+                    let tunnel = entry.tunnel;
+                    let text = match entry.previous_url {
+                        Some(previous_url) => format!(
+                            "<strike>{}</strike><br>{}",
+                            escape(previous_url),
+                            link(&tunnel.public_url)
+                        ),
+                        None => link(&tunnel.public_url),
+                    };
+                    tunnel_widget(tunnel, &text)
+                }));
+                widgets
+            }
+        };
+
+        let title = templates
+            .title(event, str::to_owned)
+            .unwrap_or_else(|| event.title().to_string());
+        let subtitle = templates
+            .footer(event, str::to_owned)
+            .unwrap_or_else(|| format!("LinkUp Ngrok Manager • {}", Utc::now().to_rfc3339()));
+        self.post_card(url, &title, &subtitle, widgets).await
+    }
+
+    async fn post_card(
+        &self,
+        url: &str,
+        title: &str,
+        subtitle: &str,
+        widgets: Vec<Value>,
+    ) -> Result<()> {
+        let payload = json!({
+            "cardsV2": [{
+                "cardId": "linkup",
+                "card": {
+                    "header": {
+                        "title": title,
+                        "subtitle": subtitle,
+                    },
+                    "sections": [{ "widgets": widgets }],
+                }
+            }]
+        });
+
+        let request = self.client.post(url).json(&payload);
+        let (status, body) = delivery::send("Google Chat webhook", request)
+            .await
+            .context("Failed to send Google Chat webhook")?;
+
+        if !status.is_success() {
+            return Err(anyhow::anyhow!(
+                "Google Chat webhook failed with status {status}: {body}"
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+fn paragraph(text: &str) -> Value {
+    json!({ "textParagraph": { "text": text } })
+}
+
+fn tunnel_widget(tunnel: &NgrokTunnel, text: &str) -> Value {
+    let mut widget = json!({
+        "decoratedText": {
+            "topLabel": format!("🔗 {} → {}", tunnel.proto.to_uppercase(), tunnel.config.addr),
+            "text": text,
+            "wrapText": true,
+        }
+    });
+    // Google Chat only opens http(s) links, so TCP tunnels get no button.
+    if tunnel.public_url.starts_with("http") {
+        widget["decoratedText"]["button"] = json!({
+            "text": "Open",
+            "onClick": { "openLink": { "url": tunnel.public_url } },
+        });
+    }
+    widget
+}
+
+fn link(url: &str) -> String {
+    format!(
+        "<a href=\"{}\">{}</a>",
+        escape(url),
+        escape(display_url(url))
+    )
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
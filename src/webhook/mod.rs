@@ -1,5 +1,15 @@
+pub mod delivery;
 pub mod discord;
+pub mod event;
+pub mod filter;
 pub mod generic;
+pub mod google_chat;
 pub mod notifier;
+pub mod signing;
+pub mod slack;
+pub mod teams;
+pub mod telegram;
+pub mod template;
 
+pub use event::NotificationEvent;
 pub use notifier::WebhookNotifier;
@@ -1,139 +1,175 @@
-use anyhow::Result;
-use log::{error, info};
-use serde_json::{Value, json};
-use std::fmt::Write;
+use anyhow::{Result, anyhow};
+use log::{error, info, warn};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tokio::task::JoinHandle;
 
 use super::discord::DiscordWebhook;
+use super::event::NotificationEvent;
+use super::filter::WebhookFilter;
 use super::generic::GenericWebhook;
-use crate::config::Webhook;
-use crate::ngrok::NgrokTunnel;
+use super::google_chat::GoogleChatWebhook;
+use super::slack::SlackWebhook;
+use super::teams::TeamsWebhook;
+use super::telegram::TelegramWebhook;
+use super::template::MessageTemplates;
+use crate::config::{Webhook, WebhookKind};
 
-const COLOR_ERROR: u32 = 15_158_332;
-const COLOR_RESTARTING: u32 = 16_776_960;
-const COLOR_STOPPED: u32 = 9_807_270;
-const COLOR_STARTED: u32 = 5_814_783;
+const QUEUE_CAPACITY: usize = 64;
 
-pub struct WebhookNotifier {
-    webhooks: Vec<Webhook>,
+struct Channels {
     discord: DiscordWebhook,
     generic: GenericWebhook,
+    slack: SlackWebhook,
+    telegram: TelegramWebhook,
+    teams: TeamsWebhook,
+    google_chat: GoogleChatWebhook,
 }
 
-impl WebhookNotifier {
-    pub fn new(webhooks: Vec<Webhook>) -> Self {
+impl Channels {
+    fn new() -> Self {
         Self {
-            webhooks: webhooks.into_iter().filter(|w| w.enabled).collect(),
             discord: DiscordWebhook::new(),
             generic: GenericWebhook::new(),
+            slack: SlackWebhook::new(),
+            telegram: TelegramWebhook::new(),
+            teams: TeamsWebhook::new(),
+            google_chat: GoogleChatWebhook::new(),
         }
     }
 
-    pub async fn notify_tunnel_created(
+    async fn send(
         &self,
-        instance_name: &str,
-        tunnels: &[NgrokTunnel],
-    ) -> Result<()> {
-        for webhook in &self.webhooks {
-            match webhook.kind.as_str() {
-                "discord" => {
-                    let fields: Vec<Value> = tunnels
-                        .iter()
-                        .map(|tunnel| {
-                            let clean_url = Self::clean_url(&tunnel.public_url);
-                            json!({
-                                "name": format!("🔗 {}", tunnel.proto.to_uppercase()),
-                                "value": format!("```\n{clean_url}\n```"),
-                                "inline": false,
-                            })
-                        })
-                        .collect();
-
-                    if let Err(e) = self
-                        .discord
-                        .send_tunnels(&webhook.url, instance_name, fields)
-                        .await
-                    {
-                        error!("Failed to send Discord webhook '{}': {e}", webhook.name);
-                    } else {
-                        info!("Sent Discord tunnel notification to '{}'", webhook.name);
-                    }
-                }
-                "generic" => {
-                    let mut message =
-                        format!("Ngrok tunnels for '{instance_name}' are ready:\n");
-                    for tunnel in tunnels {
-                        let _ =
-                            writeln!(message, "• {} → {}", tunnel.public_url, tunnel.config.addr);
-                    }
-                    if let Err(e) = self.generic.send_message(&webhook.url, &message).await {
-                        error!("Failed to send generic webhook '{}': {e}", webhook.name);
-                    } else {
-                        info!("Sent generic notification to '{}'", webhook.name);
-                    }
-                }
-                _ => {
-                    error!("Unknown webhook type: {}", webhook.kind);
-                }
-            }
+        webhook: &Webhook,
+        templates: &MessageTemplates,
+        event: &NotificationEvent,
+    ) -> (&'static str, Result<()>) {
+        match webhook.kind {
+            WebhookKind::Discord => (
+                "Discord",
+                self.discord.send(&webhook.url, event, templates).await,
+            ),
+            WebhookKind::Slack => (
+                "Slack",
+                self.slack.send(&webhook.url, event, templates).await,
+            ),
+            WebhookKind::Teams => (
+                "Teams",
+                self.teams.send(&webhook.url, event, templates).await,
+            ),
+            WebhookKind::GoogleChat => (
+                "Google Chat",
+                self.google_chat.send(&webhook.url, event, templates).await,
+            ),
+            WebhookKind::Telegram => (
+                "Telegram",
+                self.telegram.send(webhook, event, templates).await,
+            ),
+            WebhookKind::Generic => (
+                "generic",
+                self.generic.send(webhook, event, templates).await,
+            ),
+            // Rejected when the config is loaded.
+            WebhookKind::Unknown => ("unknown", Err(anyhow!("unsupported webhook type"))),
         }
-        Ok(())
     }
+}
 
-    pub async fn notify_error(&self, instance_name: &str, error: &str) -> Result<()> {
-        let message = format!("❌ LinkUp Error: Instance '{instance_name}' - {error}");
-        self.send_notification(&message).await
-    }
+struct Queue {
+    webhook: String,
+    filter: WebhookFilter,
+    sender: mpsc::Sender<Arc<NotificationEvent>>,
+}
+
+// Each webhook gets its own queue and delivery task, so retries against a slow
+// or rate-limited endpoint never hold up the monitor or the other webhooks.
+pub struct WebhookNotifier {
+    queues: Vec<Queue>,
+    workers: Vec<JoinHandle<()>>,
+}
 
-    pub async fn notify_restart(&self, instance_name: &str) -> Result<()> {
-        let message = format!("🔄 LinkUp: Restarting ngrok instance '{instance_name}'");
-        self.send_notification(&message).await
+impl WebhookNotifier {
+    pub fn new(webhooks: Vec<Webhook>) -> Self {
+        let channels = Arc::new(Channels::new());
+        let mut queues = Vec::new();
+        let mut workers = Vec::new();
+        for webhook in webhooks.into_iter().filter(|w| w.enabled) {
+            let (sender, receiver) = mpsc::channel(QUEUE_CAPACITY);
+            queues.push(Queue {
+                webhook: webhook.name.clone(),
+                filter: WebhookFilter::from_webhook(&webhook),
+                sender,
+            });
+            workers.push(tokio::spawn(deliver(webhook, channels.clone(), receiver)));
+        }
+        Self { queues, workers }
     }
 
-    async fn send_notification(&self, message: &str) -> Result<()> {
-        for webhook in &self.webhooks {
-            match webhook.kind.as_str() {
-                "discord" => {
-                    let (title, color) = Self::discord_style(message);
-                    if let Err(e) = self
-                        .discord
-                        .send_message(&webhook.url, title, message, color)
-                        .await
-                    {
-                        error!("Failed to send Discord webhook '{}': {e}", webhook.name);
-                    } else {
-                        info!("Sent Discord notification to '{}'", webhook.name);
-                    }
-                }
-                "generic" => {
-                    if let Err(e) = self.generic.send_message(&webhook.url, message).await {
-                        error!("Failed to send generic webhook '{}': {e}", webhook.name);
-                    } else {
-                        info!("Sent generic notification to '{}'", webhook.name);
-                    }
-                }
-                _ => {
-                    error!("Unknown webhook type: {}", webhook.kind);
-                }
+    pub fn notify(&self, event: NotificationEvent) {
+        let event = Arc::new(event);
+        for queue in self
+            .queues
+            .iter()
+            .filter(|queue| queue.filter.allows(&event))
+        {
+            match queue.sender.try_send(event.clone()) {
+                Ok(()) => {}
+                Err(TrySendError::Full(_)) => warn!(
+                    "Delivery queue for webhook '{}' is full, dropping {} notification",
+                    queue.webhook,
+                    event.kind()
+                ),
+                Err(TrySendError::Closed(_)) => error!(
+                    "Delivery task for webhook '{}' has stopped, dropping {} notification",
+                    queue.webhook,
+                    event.kind()
+                ),
             }
         }
-        Ok(())
     }
 
-    fn discord_style(message: &str) -> (&'static str, u32) {
-        if message.contains("Error") {
-            ("❌ Error", COLOR_ERROR)
-        } else if message.contains("Restarting") {
-            ("🔄 Restarting", COLOR_RESTARTING)
-        } else if message.contains("stopped") {
-            ("🛑 Stopped", COLOR_STOPPED)
-        } else {
-            ("🚀 Started", COLOR_STARTED)
+    // Closes the queues and waits for already queued notifications to be delivered.
+    pub async fn flush(self, timeout: Duration) {
+        drop(self.queues);
+        let workers = self.workers;
+        let pending = workers.len();
+        let drained = tokio::time::timeout(timeout, async {
+            for worker in workers {
+                let _ = worker.await;
+            }
+        })
+        .await;
+        if drained.is_err() {
+            warn!("Timed out waiting for {pending} webhook delivery queue(s) to drain");
         }
     }
+}
 
-    fn clean_url(url: &str) -> String {
-        url.split_once("://")
-            .map_or(url, |(_, rest)| rest)
-            .to_string()
+async fn deliver(
+    webhook: Webhook,
+    channels: Arc<Channels>,
+    mut receiver: mpsc::Receiver<Arc<NotificationEvent>>,
+) {
+    // Telegram rejects a message with any unescaped MarkdownV2 character, so
+    // its template text is escaped unless the webhook opts into raw markup.
+    let escape_text = webhook.kind == WebhookKind::Telegram && !webhook.templates.raw;
+    // Templates are validated when the config is loaded.
+    let templates =
+        MessageTemplates::from_config(&webhook.templates, escape_text).unwrap_or_else(|e| {
+            error!("Invalid templates for webhook '{}': {e}", webhook.name);
+            MessageTemplates::default()
+        });
+    while let Some(event) = receiver.recv().await {
+        match channels.send(&webhook, &templates, &event).await {
+            (channel, Ok(())) => info!(
+                "Sent {channel} {} notification to '{}'",
+                event.kind(),
+                webhook.name
+            ),
+            (channel, Err(e)) => {
+                error!("Failed to send {channel} webhook '{}': {e}", webhook.name)
+            }
+        }
     }
 }
@@ -0,0 +1,58 @@
+use anyhow::Result;
+use openssl::hash::MessageDigest;
+use openssl::pkey::PKey;
+use openssl::sign::Signer;
+use std::fmt::Write;
+
+pub const DELIVERY_HEADER: &str = "X-LinkUp-Delivery";
+pub const TIMESTAMP_HEADER: &str = "X-LinkUp-Timestamp";
+pub const SIGNATURE_HEADER: &str = "X-LinkUp-Signature-256";
+
+// A random (version 4) UUID identifying one notification, shared by its retries.
+pub fn delivery_id() -> String {
+    let bits = (fastrand::u128(..) & !(0xf << 76) & !(0x3 << 62)) | (0x4 << 76) | (0x2 << 62);
+    let hex = format!("{bits:032x}");
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+// Signs `{timestamp}.{delivery_id}.{body}` with HMAC-SHA256 and returns the
+// `sha256=<hex>` header value.
+pub fn sign(secret: &str, timestamp: i64, delivery_id: &str, body: &[u8]) -> Result<String> {
+    let key = PKey::hmac(secret.as_bytes())?;
+    let mut signer = Signer::new(MessageDigest::sha256(), &key)?;
+    signer.update(format!("{timestamp}.{delivery_id}.").as_bytes())?;
+    signer.update(body)?;
+
+    let mut signature = String::from("sha256=");
+    for byte in signer.sign_to_vec()? {
+        let _ = write!(signature, "{byte:02x}");
+    }
+    Ok(signature)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signs_timestamp_delivery_id_and_body() {
+        let signature = sign(
+            "It's a Secret to Everybody",
+            1_700_000_000,
+            "3f0b6a7e-9a4c-4d2e-8b1f-2c5d7e9a1b3c",
+            br#"{"event":"stopped","instance":"api"}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            signature,
+            "sha256=b98e5e7df183cbf89ffb635c3435b8f253ec98d84ce259fc6e763bc21eac3e8e"
+        );
+    }
+}
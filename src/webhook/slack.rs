@@ -0,0 +1,142 @@
+use anyhow::{Context, Result};
+use chrono::Utc;
+use reqwest::Client;
+use serde_json::{Value, json};
+
+use super::delivery;
+use super::event::{NotificationEvent, display_url};
+use super::template::MessageTemplates;
+
+pub struct SlackWebhook {
+    client: Client,
+}
+
+impl SlackWebhook {
+    pub fn new() -> Self {
+        Self {
+            client: delivery::client(),
+        }
+    }
+
+    pub async fn send(
+        &self,
+        url: &str,
+        event: &NotificationEvent,
+        templates: &MessageTemplates,
+    ) -> Result<()> {
+        let title = templates
+            .title(event, str::to_owned)
+            .unwrap_or_else(|| event.title().to_string());
+        let mut blocks = vec![header(&title)];
+        match templates.body(event, escape) {
+            Some(body) => blocks.push(section(&body)),
+            None => {
+                blocks.push(section(&format!(
+                    "*{}*\n{}",
+                    escape(event.instance()),
+                    escape(&event.summary())
+                )));
+                blocks.extend(event.tunnel_entries().iter().map(|entry| {
+                    let tunnel = entry.tunnel;
+                    let mut text = format!(
+                        "🔗 *{}* → `{}`\n",
+                        escape(&tunnel.proto.to_uppercase()),
+                        escape(&tunnel.config.addr)
+                    );
+                    if let Some(previous_url) = entry.previous_url {
+                        text.push_str(&format!("~{}~\n", escape(previous_url)));
+                    }
+                    text.push_str(&link(&tunnel.public_url));
+                    section(&text)
+                }));
+            }
+        }
+        blocks.push(footer(
+            &templates
+                .footer(event, escape)
+                .unwrap_or_else(default_footer),
+        ));
+
+        self.post_payload(url, &event.message(), event.color(), blocks)
+            .await
+    }
+
+    async fn post_payload(
+        &self,
+        url: &str,
+        fallback: &str,
+        color: u32,
+        blocks: Vec<Value>,
+    ) -> Result<()> {
+        let payload = json!({
+            "text": fallback,
+            "attachments": [{
+                "color": format!("#{color:06X}"),
+                "blocks": blocks,
+            }]
+        });
+
+        let request = self.client.post(url).json(&payload);
+        let (status, body) = delivery::send("Slack webhook", request)
+            .await
+            .context("Failed to send Slack webhook")?;
+
+        if !status.is_success() {
+            return Err(anyhow::anyhow!(
+                "Slack webhook failed with status {status}: {body}"
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+fn header(text: &str) -> Value {
+    json!({
+        "type": "header",
+        "text": {
+            "type": "plain_text",
+            "text": text,
+            "emoji": true,
+        }
+    })
+}
+
+fn section(text: &str) -> Value {
+    json!({
+        "type": "section",
+        "text": {
+            "type": "mrkdwn",
+            "text": text,
+        }
+    })
+}
+
+fn footer(text: &str) -> Value {
+    json!({
+        "type": "context",
+        "elements": [{
+            "type": "mrkdwn",
+            "text": text,
+        }]
+    })
+}
+
+fn default_footer() -> String {
+    let now = Utc::now();
+    format!(
+        "LinkUp Ngrok Manager • <!date^{}^{{date_short_pretty}} {{time}}|{}>",
+        now.timestamp(),
+        now.to_rfc3339()
+    )
+}
+
+fn link(url: &str) -> String {
+    format!("<{}|{}>", escape(url), escape(display_url(url)))
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
@@ -0,0 +1,167 @@
+use anyhow::{Context, Result};
+use chrono::Utc;
+use reqwest::Client;
+use serde_json::{Value, json};
+
+use super::delivery;
+use super::event::NotificationEvent;
+use super::template::MessageTemplates;
+use crate::ngrok::NgrokTunnel;
+
+pub struct TeamsWebhook {
+    client: Client,
+}
+
+impl TeamsWebhook {
+    pub fn new() -> Self {
+        Self {
+            client: delivery::client(),
+        }
+    }
+
+    pub async fn send(
+        &self,
+        url: &str,
+        event: &NotificationEvent,
+        templates: &MessageTemplates,
+    ) -> Result<()> {
+        let entries = event.tunnel_entries();
+        let title = templates
+            .title(event, str::to_owned)
+            .unwrap_or_else(|| event.title().to_string());
+        let mut body = vec![heading(&title, container_style(event))];
+        match templates.body(event, str::to_owned) {
+            Some(text) => body.push(text_block(&text)),
+            None => {
+                body.push(text_block(&format!(
+                    "**{}**\n\n{}",
+                    event.instance(),
+                    event.summary()
+                )));
+                body.extend(entries.iter().map(|entry| {
+                    let tunnel = entry.tunnel;
+                    let url_text = match entry.previous_url {
+                        Some(previous_url) => {
+                            format!("[{0}]({0}) (was {previous_url})", tunnel.public_url)
+                        }
+                        None => format!("[{0}]({0})", tunnel.public_url),
+                    };
+                    tunnel_facts(tunnel, &url_text)
+                }));
+            }
+        }
+        body.push(footer(
+            &templates
+                .footer(event, str::to_owned)
+                .unwrap_or_else(|| format!("LinkUp Ngrok Manager • {}", Utc::now().to_rfc3339())),
+        ));
+
+        let actions = open_actions(entries.iter().map(|entry| entry.tunnel));
+        self.post_card(url, body, actions).await
+    }
+
+    async fn post_card(&self, url: &str, body: Vec<Value>, actions: Vec<Value>) -> Result<()> {
+        let payload = json!({
+            "type": "message",
+            "attachments": [{
+                "contentType": "application/vnd.microsoft.card.adaptive",
+                "contentUrl": null,
+                "content": {
+                    "$schema": "http://adaptivecards.io/schemas/adaptive-card.json",
+                    "type": "AdaptiveCard",
+                    "version": "1.4",
+                    "body": body,
+                    "actions": actions,
+                    "msteams": { "width": "Full" },
+                }
+            }]
+        });
+
+        let request = self.client.post(url).json(&payload);
+        let (status, body) = delivery::send("Teams webhook", request)
+            .await
+            .context("Failed to send Teams webhook")?;
+
+        // Legacy Office 365 connectors answer 200 even when delivery failed.
+        if !status.is_success() || body.contains("delivery failed") {
+            return Err(anyhow::anyhow!(
+                "Teams webhook failed with status {status}: {body}"
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+fn container_style(event: &NotificationEvent) -> &'static str {
+    match event {
+        NotificationEvent::TunnelsReady { .. } => "good",
+        NotificationEvent::TunnelUrlChanged { .. } => "accent",
+        NotificationEvent::InstanceError { .. } | NotificationEvent::GaveUp { .. } => "attention",
+        NotificationEvent::Restarting { .. } => "warning",
+        NotificationEvent::Stopped { .. } => "emphasis",
+    }
+}
+
+fn heading(title: &str, style: &str) -> Value {
+    json!({
+        "type": "Container",
+        "style": style,
+        "bleed": true,
+        "items": [{
+            "type": "TextBlock",
+            "text": title,
+            "size": "Large",
+            "weight": "Bolder",
+            "wrap": true,
+        }]
+    })
+}
+
+fn text_block(text: &str) -> Value {
+    json!({
+        "type": "TextBlock",
+        "text": text,
+        "wrap": true,
+    })
+}
+
+fn tunnel_facts(tunnel: &NgrokTunnel, url_text: &str) -> Value {
+    json!({
+        "type": "FactSet",
+        "separator": true,
+        "facts": [
+            { "title": "🔗 Protocol", "value": tunnel.proto.to_uppercase() },
+            { "title": "Local", "value": tunnel.config.addr },
+            { "title": "URL", "value": url_text },
+        ]
+    })
+}
+
+fn open_actions<'a>(tunnels: impl Iterator<Item = &'a NgrokTunnel>) -> Vec<Value> {
+    tunnels
+        .filter(|tunnel| tunnel.public_url.starts_with("http"))
+        .map(|tunnel| {
+            let name = if tunnel.name.is_empty() {
+                tunnel.config.addr.as_str()
+            } else {
+                tunnel.name.as_str()
+            };
+            json!({
+                "type": "Action.OpenUrl",
+                "title": format!("Open {name}"),
+                "url": tunnel.public_url,
+            })
+        })
+        .collect()
+}
+
+fn footer(text: &str) -> Value {
+    json!({
+        "type": "TextBlock",
+        "text": text,
+        "size": "Small",
+        "isSubtle": true,
+        "wrap": true,
+    })
+}
@@ -0,0 +1,150 @@
+use anyhow::{Context, Result, anyhow};
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::json;
+use std::fmt::Write;
+
+use super::delivery;
+use super::event::{NotificationEvent, display_url};
+use super::template::MessageTemplates;
+use crate::config::Webhook;
+
+const DEFAULT_API_URL: &str = "https://api.telegram.org";
+
+#[derive(Deserialize)]
+struct ApiResponse {
+    ok: bool,
+    #[serde(default)]
+    description: Option<String>,
+}
+
+pub struct TelegramWebhook {
+    client: Client,
+}
+
+impl TelegramWebhook {
+    pub fn new() -> Self {
+        Self {
+            client: delivery::client(),
+        }
+    }
+
+    pub async fn send(
+        &self,
+        webhook: &Webhook,
+        event: &NotificationEvent,
+        templates: &MessageTemplates,
+    ) -> Result<()> {
+        let title = templates
+            .title(event, escape)
+            .unwrap_or_else(|| escape(event.title()));
+        let mut text = format!("*{title}*\n\n");
+        match templates.body(event, escape) {
+            Some(body) => text.push_str(&body),
+            None => {
+                let _ = writeln!(
+                    text,
+                    "*{}*\n{}",
+                    escape(event.instance()),
+                    escape(&event.summary())
+                );
+                for entry in event.tunnel_entries() {
+                    let tunnel = entry.tunnel;
+                    let _ = write!(
+                        text,
+                        "\n🔗 *{}* → {}\n",
+                        escape(&tunnel.proto.to_uppercase()),
+                        code(&tunnel.config.addr)
+                    );
+                    if let Some(previous_url) = entry.previous_url {
+                        let _ = writeln!(text, "~{}~", escape(previous_url));
+                    }
+                    let _ = writeln!(text, "{}", link(&tunnel.public_url));
+                }
+            }
+        }
+        if let Some(footer) = templates.footer(event, escape) {
+            let _ = write!(text, "\n\n_{footer}_");
+        }
+        self.send_text(webhook, &text).await
+    }
+
+    async fn send_text(&self, webhook: &Webhook, text: &str) -> Result<()> {
+        let api_url = if webhook.url.is_empty() {
+            DEFAULT_API_URL
+        } else {
+            webhook.url.trim_end_matches('/')
+        };
+        let url = format!("{api_url}/bot{}/sendMessage", webhook.bot_token);
+
+        let mut payload = json!({
+            "chat_id": webhook.chat_id,
+            "text": text,
+            "parse_mode": "MarkdownV2",
+            "disable_web_page_preview": true,
+        });
+        if let Some(thread_id) = webhook.message_thread_id {
+            payload["message_thread_id"] = json!(thread_id);
+        }
+
+        // The bot token is part of the URL; `delivery::send` keeps it out of errors.
+        let request = self.client.post(&url).json(&payload);
+        let (status, body) = delivery::send("Telegram message", request)
+            .await
+            .context("Failed to send Telegram message")?;
+
+        match serde_json::from_str::<ApiResponse>(&body) {
+            Ok(api_response) if status.is_success() && api_response.ok => Ok(()),
+            Ok(ApiResponse {
+                description: Some(description),
+                ..
+            }) => Err(anyhow!(
+                "Telegram API failed with status {status}: {description}"
+            )),
+            _ => Err(anyhow!("Telegram API failed with status {status}: {body}")),
+        }
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(
+            c,
+            '_' | '*'
+                | '['
+                | ']'
+                | '('
+                | ')'
+                | '~'
+                | '`'
+                | '>'
+                | '#'
+                | '+'
+                | '-'
+                | '='
+                | '|'
+                | '{'
+                | '}'
+                | '.'
+                | '!'
+                | '\\'
+        ) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn code(text: &str) -> String {
+    format!("`{}`", text.replace('\\', "\\\\").replace('`', "\\`"))
+}
+
+fn link(url: &str) -> String {
+    format!(
+        "[{}]({})",
+        escape(display_url(url)),
+        url.replace('\\', "\\\\").replace(')', "\\)")
+    )
+}
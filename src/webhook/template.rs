@@ -0,0 +1,396 @@
+use chrono::Utc;
+use serde_json::Value;
+
+use super::event::{NotificationEvent, TunnelEntry};
+use crate::config::WebhookTemplates;
+
+#[derive(Debug, Clone, Copy)]
+enum Var {
+    Event,
+    Instance,
+    Title,
+    Summary,
+    Error,
+    Restarts,
+    Timestamp,
+    Name,
+    PublicUrl,
+    PreviousUrl,
+    Addr,
+    Proto,
+}
+
+impl Var {
+    fn parse(name: &str) -> Result<Self, String> {
+        Ok(match name {
+            "event" => Self::Event,
+            "instance" => Self::Instance,
+            "title" => Self::Title,
+            "summary" => Self::Summary,
+            "error" => Self::Error,
+            "restarts" => Self::Restarts,
+            "timestamp" => Self::Timestamp,
+            "name" => Self::Name,
+            "public_url" => Self::PublicUrl,
+            "previous_url" => Self::PreviousUrl,
+            "addr" => Self::Addr,
+            "proto" => Self::Proto,
+            _ => return Err(format!("unknown variable '{name}'")),
+        })
+    }
+
+    fn value(self, event: &NotificationEvent, entry: Option<&TunnelEntry>) -> String {
+        let tunnel = entry.map(|entry| entry.tunnel);
+        match self {
+            Self::Event => event.kind().to_string(),
+            Self::Instance => event.instance().to_string(),
+            Self::Title => event.title().to_string(),
+            Self::Summary => event.summary(),
+            Self::Error => event.error().unwrap_or_default().to_string(),
+            Self::Restarts => match event {
+                NotificationEvent::GaveUp { restarts, .. } => restarts.to_string(),
+                _ => String::new(),
+            },
+            Self::Timestamp => Utc::now().to_rfc3339(),
+            Self::Name => tunnel.map(|t| t.name.clone()).unwrap_or_default(),
+            Self::PublicUrl => tunnel.map(|t| t.public_url.clone()).unwrap_or_default(),
+            Self::PreviousUrl => entry
+                .and_then(|entry| entry.previous_url)
+                .unwrap_or_default()
+                .to_string(),
+            Self::Addr => tunnel.map(|t| t.config.addr.clone()).unwrap_or_default(),
+            Self::Proto => tunnel.map(|t| t.proto.clone()).unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Text(String),
+    Var(Var),
+    Tunnels(Vec<Node>),
+}
+
+// A minimal mustache-like template: `{{variable}}` substitutions and a
+// `{{#tunnels}}...{{/tunnels}}` loop. Outside the loop, tunnel variables refer
+// to the first tunnel.
+#[derive(Debug, Clone)]
+pub struct Template {
+    nodes: Vec<Node>,
+}
+
+impl Template {
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let mut stack: Vec<Vec<Node>> = vec![Vec::new()];
+        let mut rest = source;
+        while let Some(start) = rest.find("{{") {
+            push_text(&mut stack, &rest[..start]);
+            let after = &rest[start + 2..];
+            let end = after
+                .find("}}")
+                .ok_or_else(|| "unterminated '{{'".to_string())?;
+            let tag = after[..end].trim();
+            rest = &after[end + 2..];
+
+            if let Some(section) = tag.strip_prefix('#') {
+                if section.trim() != "tunnels" {
+                    return Err(format!(
+                        "unknown section '{}', only 'tunnels' can be looped over",
+                        section.trim()
+                    ));
+                }
+                if stack.len() > 1 {
+                    return Err("'{{#tunnels}}' cannot be nested".to_string());
+                }
+                stack.push(Vec::new());
+            } else if let Some(section) = tag.strip_prefix('/') {
+                if section.trim() != "tunnels" || stack.len() == 1 {
+                    return Err(format!(
+                        "unexpected closing tag '{{{{/{}}}}}'",
+                        section.trim()
+                    ));
+                }
+                let body = stack.pop().unwrap_or_default();
+                if let Some(nodes) = stack.last_mut() {
+                    nodes.push(Node::Tunnels(body));
+                }
+            } else if let Some(nodes) = stack.last_mut() {
+                nodes.push(Node::Var(Var::parse(tag)?));
+            }
+        }
+        push_text(&mut stack, rest);
+
+        if stack.len() > 1 {
+            return Err("'{{#tunnels}}' is missing its '{{/tunnels}}'".to_string());
+        }
+        Ok(Self {
+            nodes: stack.pop().unwrap_or_default(),
+        })
+    }
+
+    // Renders the template, passing every substituted value through `escape`
+    // so channels can keep their markup intact.
+    pub fn render(&self, event: &NotificationEvent, escape: impl Fn(&str) -> String) -> String {
+        self.render_with(event, &escape, false)
+    }
+
+    // Like `render`, but escapes the template text as well.
+    pub fn render_escaped(
+        &self,
+        event: &NotificationEvent,
+        escape: impl Fn(&str) -> String,
+    ) -> String {
+        self.render_with(event, &escape, true)
+    }
+
+    fn render_with(
+        &self,
+        event: &NotificationEvent,
+        escape: &impl Fn(&str) -> String,
+        escape_text: bool,
+    ) -> String {
+        let entries = event.tunnel_entries();
+        let mut out = String::new();
+        render_nodes(
+            &self.nodes,
+            event,
+            entries.first(),
+            &entries,
+            escape,
+            escape_text,
+            &mut out,
+        );
+        out
+    }
+}
+
+fn push_text(stack: &mut [Vec<Node>], text: &str) {
+    if let Some(nodes) = stack.last_mut()
+        && !text.is_empty()
+    {
+        nodes.push(Node::Text(text.to_string()));
+    }
+}
+
+fn render_nodes(
+    nodes: &[Node],
+    event: &NotificationEvent,
+    entry: Option<&TunnelEntry>,
+    entries: &[TunnelEntry],
+    escape: &impl Fn(&str) -> String,
+    escape_text: bool,
+    out: &mut String,
+) {
+    for node in nodes {
+        match node {
+            Node::Text(text) if escape_text => out.push_str(&escape(text)),
+            Node::Text(text) => out.push_str(text),
+            Node::Var(var) => out.push_str(&escape(&var.value(event, entry))),
+            Node::Tunnels(body) => {
+                for entry in entries {
+                    render_nodes(body, event, Some(entry), entries, escape, escape_text, out);
+                }
+            }
+        }
+    }
+}
+
+// A JSON value whose strings are templates, used as a custom webhook payload.
+#[derive(Debug, Clone)]
+pub enum PayloadTemplate {
+    Text(Template),
+    Array(Vec<PayloadTemplate>),
+    Object(Vec<(String, PayloadTemplate)>),
+    Literal(Value),
+}
+
+impl PayloadTemplate {
+    pub fn parse(value: &Value) -> Result<Self, String> {
+        Self::parse_at("payload", value)
+    }
+
+    fn parse_at(path: &str, value: &Value) -> Result<Self, String> {
+        Ok(match value {
+            Value::String(source) => {
+                Self::Text(Template::parse(source).map_err(|e| format!("{path}: {e}"))?)
+            }
+            Value::Array(items) => Self::Array(
+                items
+                    .iter()
+                    .enumerate()
+                    .map(|(index, item)| Self::parse_at(&format!("{path}[{index}]"), item))
+                    .collect::<Result<_, _>>()?,
+            ),
+            Value::Object(fields) => Self::Object(
+                fields
+                    .iter()
+                    .map(|(key, item)| {
+                        Ok((key.clone(), Self::parse_at(&format!("{path}.{key}"), item)?))
+                    })
+                    .collect::<Result<_, String>>()?,
+            ),
+            other => Self::Literal(other.clone()),
+        })
+    }
+
+    pub fn render(&self, event: &NotificationEvent) -> Value {
+        match self {
+            Self::Text(template) => Value::String(template.render(event, str::to_owned)),
+            Self::Array(items) => {
+                Value::Array(items.iter().map(|item| item.render(event)).collect())
+            }
+            Self::Object(fields) => Value::Object(
+                fields
+                    .iter()
+                    .map(|(key, item)| (key.clone(), item.render(event)))
+                    .collect(),
+            ),
+            Self::Literal(value) => value.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MessageTemplates {
+    title: Option<Template>,
+    body: Option<Template>,
+    footer: Option<Template>,
+    payload: Option<PayloadTemplate>,
+    escape_text: bool,
+}
+
+impl MessageTemplates {
+    // With `escape_text`, the template text goes through the channel's escape
+    // function too, not just the substituted values.
+    pub fn from_config(config: &WebhookTemplates, escape_text: bool) -> Result<Self, String> {
+        let parse = |field: &str, source: &Option<String>| {
+            source
+                .as_deref()
+                .map(Template::parse)
+                .transpose()
+                .map_err(|e| format!("{field}: {e}"))
+        };
+        Ok(Self {
+            title: parse("title", &config.title)?,
+            body: parse("body", &config.body)?,
+            footer: parse("footer", &config.footer)?,
+            payload: config
+                .payload
+                .as_ref()
+                .map(PayloadTemplate::parse)
+                .transpose()?,
+            escape_text,
+        })
+    }
+
+    fn render(
+        &self,
+        template: &Template,
+        event: &NotificationEvent,
+        escape: impl Fn(&str) -> String,
+    ) -> String {
+        if self.escape_text {
+            template.render_escaped(event, escape)
+        } else {
+            template.render(event, escape)
+        }
+    }
+
+    pub fn title(
+        &self,
+        event: &NotificationEvent,
+        escape: impl Fn(&str) -> String,
+    ) -> Option<String> {
+        self.title.as_ref().map(|t| self.render(t, event, escape))
+    }
+
+    pub fn body(
+        &self,
+        event: &NotificationEvent,
+        escape: impl Fn(&str) -> String,
+    ) -> Option<String> {
+        self.body.as_ref().map(|t| self.render(t, event, escape))
+    }
+
+    pub fn footer(
+        &self,
+        event: &NotificationEvent,
+        escape: impl Fn(&str) -> String,
+    ) -> Option<String> {
+        self.footer.as_ref().map(|t| self.render(t, event, escape))
+    }
+
+    pub fn payload(&self, event: &NotificationEvent) -> Option<Value> {
+        self.payload.as_ref().map(|t| t.render(event))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ngrok::NgrokTunnel;
+    use crate::ngrok::tunnel::NgrokTunnelConfig;
+
+    fn tunnel(name: &str, public_url: &str) -> NgrokTunnel {
+        NgrokTunnel {
+            name: name.to_string(),
+            public_url: public_url.to_string(),
+            proto: "https".to_string(),
+            config: NgrokTunnelConfig {
+                addr: "http://localhost:8080".to_string(),
+            },
+        }
+    }
+
+    #[test]
+    fn renders_variables_and_tunnel_loop() {
+        let event = NotificationEvent::TunnelsReady {
+            instance: "api".to_string(),
+            tunnels: vec![
+                tunnel("web", "https://a.ngrok.app"),
+                tunnel("admin", "https://b.ngrok.app"),
+            ],
+        };
+        let template =
+            Template::parse("{{ instance }} {{name}}:{{#tunnels}} {{public_url}}{{/tunnels}}")
+                .unwrap();
+        assert_eq!(
+            template.render(&event, str::to_owned),
+            "api web: https://a.ngrok.app https://b.ngrok.app"
+        );
+    }
+
+    #[test]
+    fn escapes_text_only_when_asked() {
+        let event = NotificationEvent::Stopped {
+            instance: "a.b".to_string(),
+        };
+        let template = Template::parse("v1.0 {{instance}}").unwrap();
+        let escape = |text: &str| text.replace('.', "\\.");
+        assert_eq!(template.render(&event, escape), "v1.0 a\\.b");
+        assert_eq!(template.render_escaped(&event, escape), "v1\\.0 a\\.b");
+    }
+
+    #[test]
+    fn rejects_malformed_templates() {
+        let error = |source: &str| Template::parse(source).unwrap_err();
+        assert_eq!(error("{{instance"), "unterminated '{{'");
+        assert_eq!(
+            error("{{#tunnels}}{{name}}"),
+            "'{{#tunnels}}' is missing its '{{/tunnels}}'"
+        );
+        assert_eq!(
+            error("{{#tunnels}}{{#tunnels}}{{/tunnels}}{{/tunnels}}"),
+            "'{{#tunnels}}' cannot be nested"
+        );
+        assert_eq!(
+            error("{{/tunnels}}"),
+            "unexpected closing tag '{{/tunnels}}'"
+        );
+        assert_eq!(
+            error("{{#instances}}{{/instances}}"),
+            "unknown section 'instances', only 'tunnels' can be looped over"
+        );
+        assert_eq!(error("{{url}}"), "unknown variable 'url'");
+    }
+}